@@ -1,8 +1,24 @@
-use std::{collections::HashMap, fmt::Write as _, sync::LazyLock};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write as _,
+    path::Path,
+    sync::LazyLock,
+};
 
 use gh_file_curler::{fetch, wrapped_first};
 use itertools::Itertools as _;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use ttf_parser::{
+    gsub::{SingleSubstitution, SubstitutionSubtable},
+    GlyphId,
+};
+use unicode_case_mapping::to_titlecase;
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::UnicodeNormalization as _;
+use unicode_script::{Script as UnicodeScriptValue, UnicodeScript as _};
+
+mod subset;
+mod woff;
 
 macro_rules! cprint {
     ($s:literal$(, $($f:expr),+)?) => {
@@ -15,17 +31,69 @@ macro_rules! cprint {
 
 #[derive(Debug, Clone)]
 pub struct FontStack {
-    pub names: Vec<String>,
-    pub map: HashMap<u32, Vec<String>>,
+    pub names: Vec<FontFamily>,
+    pub map: HashMap<u32, Vec<FontFamily>>,
+    /// codepoints left out of the coverage check by a [`CharPolicy::Report`]
+    pub invisible: Vec<u32>,
+    /// codepoints that aren't in the text but are in its [`Normalization`]
+    pub normalized: Vec<u32>,
+    /// codepoints that some font supports but that didn't fit in [`NotoizeConfig::budget`]
+    pub over_budget: Vec<u32>,
+    /// codepoints left out by a [`CharPolicy::Report`] because only fonts for
+    /// [`ScriptStatus::Excluded`] scripts support them
+    pub historic: Vec<u32>,
+    /// CJK fonts without `vert` or `vrt2`, whose punctuation won't turn in
+    /// [`NotoizeConfig::vertical`] text
+    pub no_vertical_features: Vec<FontFamily>,
+    /// CJK fonts that couldn't be fetched to check for [`NotoizeConfig::vertical`] features
+    pub unverified: Vec<FontFamily>,
+    /// the codepoints that needed coverage
+    pub codepoints: Vec<u32>,
+    /// the fonts that render enough of the text to be worth preloading; see
+    /// [`NotoizeConfig::critical_share`]
+    pub critical: Vec<FontFamily>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Font {
     pub filename: String,
-    pub fontname: String,
+    pub fontname: FontFamily,
     pub bytes: Vec<u8>,
 }
 
+/// what supports one codepoint, from [`FontStack::report`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodepointReport {
+    pub codepoint: u32,
+    /// the supporting families by script, scripts in alphabetical order
+    pub scripts: Vec<ScriptGroup>,
+    /// whether the families are for more than one script
+    pub conflict: bool,
+    /// variants of those scripts that don't support the codepoint
+    pub missing: Vec<FontFamily>,
+}
+
+/// some families for the same script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptGroup {
+    pub script: Script,
+    pub families: Vec<FontFamily>,
+}
+
+impl ScriptGroup {
+    /// groups families by script, scripts in alphabetical order
+    fn group(fonts: &[FontFamily]) -> Vec<Self> {
+        fonts
+            .iter()
+            .sorted_by_cached_key(|f| f.script().name().to_lowercase())
+            .group_by(|f| f.script())
+            .into_iter()
+            .map(|(script, g)| Self { script, families: g.cloned().collect() })
+            .collect()
+    }
+}
+
+/// the [`FontStack::report`] as text, like in `out/data`
 #[derive(Debug)]
 pub struct MapString {
     pub all: String,
@@ -33,135 +101,1303 @@ pub struct MapString {
     pub missing: String,
 }
 
-static SPECIAL_NAMES: LazyLock<[&str; 10]> = LazyLock::new(|| {
-    [
-        "Noto Color Emoji",
-        "Noto Sans ImpAramaic",
-        "Noto Sans OldSouArab",
-        "Noto Sans OldNorArab",
-        "Noto Sans InsPahlavi",
-        "Noto Sans PsaPahlavi",
-        "Noto Sans OldHung",
-        "Noto Sans Zanabazar",
-        "Noto Sans EgyptHiero",
-        "Noto Sans AnatoHiero",
-    ]
-});
-static SPECIAL_FILENAMES: LazyLock<[(&str, &str); 10]> = LazyLock::new(|| {
+/// families whose names are shortened in the overview data
+static LONG_NAMES: LazyLock<[(&str, &str); 13]> = LazyLock::new(|| {
     [
-        ("Noto Color Emoji", "NotoColorEmoji.ttf"),
-        ("Noto Sans ImpAramaic", "NotoSansImperialAramaic-Regular.ttf"),
-        ("Noto Sans OldSouArab", "NotoSansOldSouthArabian-Regular.ttf"),
-        ("Noto Sans OldNorArab", "NotoSansOldNorthArabian-Regular.ttf"),
-        ("Noto Sans InsPahlavi", "NotoSansInscriptionalPahlavi-Regular.ttf"),
-        ("Noto Sans PsaPahlavi", "NotoSansPsalterPahlavi-Regular.ttf"),
-        ("Noto Sans OldHung", "NotoSansOldHungarian-Regular.ttf"),
-        ("Noto Sans Zanabazar", "NotoSansZanabazarSquare-Regular.ttf"),
-        ("Noto Sans EgyptHiero", "NotoSansEgyptianHieroglyphs-Regular.ttf"),
-        ("Noto Sans AnatoHiero", "NotoSansAnatolianHieroglyphs-Regular.ttf"),
+        ("Sans ImpAramaic", "Sans Imperial Aramaic"),
+        ("Sans OldSouArab", "Sans Old South Arabian"),
+        ("Sans OldNorArab", "Sans Old North Arabian"),
+        ("Sans InsPahlavi", "Sans Inscriptional Pahlavi"),
+        ("Sans PsaPahlavi", "Sans Psalter Pahlavi"),
+        ("Sans OldHung", "Sans Old Hungarian"),
+        ("Sans Zanabazar", "Sans Zanabazar Square"),
+        ("Sans EgyptHiero", "Sans Egyptian Hieroglyphs"),
+        ("Sans AnatoHiero", "Sans Anatolian Hieroglyphs"),
+        ("Sans OldPersian", "Sans Old Persian"),
+        ("Sans OldSogdian", "Sans Old Sogdian"),
+        ("Sans PauCinHau", "Sans Pau Cin Hau"),
+        ("Sans WarangCiti", "Sans Warang Citi"),
     ]
 });
 
+/// a noto family, named like in the overview data (`Sans Adlam Unjoined`, no "Noto ")
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String")]
+pub struct FontFamily(String);
+
+/// through [`FontFamily::new`], so names with "Noto " deserialize to the same family
+impl From<String> for FontFamily {
+    fn from(name: String) -> Self { Self::new(&name) }
+}
+
+/// the first word of a family name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesignClass {
+    Sans,
+    Serif,
+    Kufi,
+    Naskh,
+    Nastaliq,
+    Rashi,
+    Fangsong,
+    Traditional,
+    Color,
+    /// Music and Znamenny Musical Notation
+    Other,
+}
+
+/// what sets a family apart from the others for its script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Qualifier {
+    Looped,
+    Unjoined,
+    Mono,
+    Eastern,
+    Western,
+    Rotated,
+    Vertical,
+    Cjk(CjkRegion),
+    /// the regional tifinagh styles, like `Adrar` or `Rhissa Ixa`
+    Style(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CjkRegion {
+    Hk,
+    Jp,
+    Kr,
+    Sc,
+    Tc,
+}
+
+impl CjkRegion {
+    /// as it appears in family names
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Hk => "HK",
+            Self::Jp => "JP",
+            Self::Kr => "KR",
+            Self::Sc => "SC",
+            Self::Tc => "TC",
+        }
+    }
+
+    /// the BCP 47 language tag the region's glyph forms are for
+    pub fn lang(self) -> &'static str {
+        match self {
+            Self::Hk => "zh-HK",
+            Self::Jp => "ja",
+            Self::Kr => "ko",
+            Self::Sc => "zh-Hans",
+            Self::Tc => "zh-Hant",
+        }
+    }
+
+    /// the fontconfig languages that should get the region's glyph forms
+    fn fontconfig_langs(self) -> &'static [&'static str] {
+        match self {
+            Self::Hk => &["zh-hk", "zh-mo"],
+            Self::Jp => &["ja"],
+            Self::Kr => &["ko"],
+            Self::Sc => &["zh-cn", "zh-sg"],
+            Self::Tc => &["zh-tw"],
+        }
+    }
+
+    /// the folder the region's fonts are in upstream
+    fn upstream_dir(self) -> &'static str {
+        match self {
+            Self::Hk => "TraditionalChineseHK",
+            Self::Jp => "Japanese",
+            Self::Kr => "Korean",
+            Self::Sc => "SimplifiedChinese",
+            Self::Tc => "TraditionalChinese",
+        }
+    }
+}
+
+impl FontFamily {
+    /// with or without "Noto "
+    pub fn new(name: &str) -> Self { Self(name.strip_prefix("Noto ").unwrap_or(name).to_string()) }
+
+    /// the name in the overview data, without "Noto "
+    pub fn name(&self) -> &str { &self.0 }
+
+    pub fn script(&self) -> Script { script(&self.0) }
+
+    pub fn class(&self) -> DesignClass {
+        match self.0.split(' ').next() {
+            Some("Sans") => DesignClass::Sans,
+            Some("Serif") => DesignClass::Serif,
+            Some("Kufi") => DesignClass::Kufi,
+            Some("Naskh") => DesignClass::Naskh,
+            Some("Nastaliq") => DesignClass::Nastaliq,
+            Some("Rashi") => DesignClass::Rashi,
+            Some("Fangsong") => DesignClass::Fangsong,
+            Some("Traditional") => DesignClass::Traditional,
+            Some("Color") => DesignClass::Color,
+            _ => DesignClass::Other,
+        }
+    }
+
+    pub fn qualifiers(&self) -> Vec<Qualifier> {
+        let words = self.0.split(' ').collect_vec();
+        if let Some(i) = words.iter().position(|w| *w == "Tifinagh") {
+            return (i + 1 < words.len())
+                .then(|| Qualifier::Style(words[i + 1..].join(" ")))
+                .into_iter()
+                .collect();
+        }
+        words
+            .iter()
+            .skip(1)
+            .filter_map(|w| match *w {
+                "Looped" => Some(Qualifier::Looped),
+                "Unjoined" => Some(Qualifier::Unjoined),
+                "Mono" => Some(Qualifier::Mono),
+                "Eastern" => Some(Qualifier::Eastern),
+                "Western" => Some(Qualifier::Western),
+                "Rotated" => Some(Qualifier::Rotated),
+                "Vertical" => Some(Qualifier::Vertical),
+                _ => None,
+            })
+            .chain(self.cjk_region().map(Qualifier::Cjk))
+            .collect()
+    }
+
+    pub fn cjk_region(&self) -> Option<CjkRegion> {
+        match self.0.strip_prefix("Sans CJK ").or_else(|| self.0.strip_prefix("Serif CJK "))? {
+            "HK" => Some(CjkRegion::Hk),
+            "JP" => Some(CjkRegion::Jp),
+            "KR" => Some(CjkRegion::Kr),
+            "SC" => Some(CjkRegion::Sc),
+            "TC" => Some(CjkRegion::Tc),
+            _ => None,
+        }
+    }
+
+    /// the CSS generic family the family counts as: `monospace`, `serif` or `sans-serif` for
+    /// everything else
+    pub fn generic_family(&self) -> &'static str {
+        if self.qualifiers().contains(&Qualifier::Mono) {
+            "monospace"
+        } else if self.class() == DesignClass::Serif {
+            "serif"
+        } else {
+            "sans-serif"
+        }
+    }
+
+    /// the family name inside the font file, for CSS
+    pub fn css_name(&self) -> String {
+        let name = LONG_NAMES.iter().find(|(short, _)| *short == self.0).map_or(&*self.0, |n| n.1);
+        format!("Noto {name}")
+    }
+
+    /// the start of the PostScript names of the family's fonts, like `NotoSansCJKjp`
+    pub fn postscript_prefix(&self) -> String {
+        match self.cjk_region() {
+            Some(region) => {
+                let class = self.0.split(' ').next().unwrap();
+                format!("Noto{class}CJK{}", region.code().to_lowercase())
+            }
+            None => self.css_name().replace([' ', '-'], ""),
+        }
+    }
+
+    /// the name of the family's regular font file upstream
+    pub fn filename(&self) -> String {
+        if self.cjk_region().is_some() {
+            format!("{}-Regular.otf", self.postscript_prefix())
+        } else if self.class() == DesignClass::Color {
+            format!("{}.ttf", self.postscript_prefix())
+        } else {
+            format!("{}-Regular.ttf", self.postscript_prefix())
+        }
+    }
+}
+
+impl std::fmt::Display for FontFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.css_name())
+    }
+}
+
+/// where a family's font file lives upstream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FontSource {
+    pub owner: String,
+    pub repo: String,
+    pub path: String,
+    pub format: FontFormat,
+    /// the size of the file in bytes, if known, for [`Manifest::sizes`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontFormat {
+    Ttf,
+    Otf,
+}
+
+impl FontSource {
+    fn new(owner: &str, repo: &str, path: String) -> Self {
+        let format = if path.ends_with(".otf") { FontFormat::Otf } else { FontFormat::Ttf };
+        Self { owner: owner.to_string(), repo: repo.to_string(), path, format, size: None }
+    }
+
+    /// the name of the file, without the folders
+    pub fn filename(&self) -> &str { self.path.rsplit('/').next().unwrap() }
+
+    pub fn fetch(&self) -> Result<Vec<u8>, String> {
+        wrapped_first(fetch(&self.owner, &self.repo, &[&self.path]))
+    }
+
+    /// whether the file is on github, for [`Manifest::validate`]. only asks for the headers, so
+    /// nothing is downloaded
+    pub fn exists_on_github(&self) -> bool {
+        let url = format!(
+            "https://raw.githubusercontent.com/{}/{}/main/{}",
+            self.owner, self.repo, self.path
+        );
+        reqwest::blocking::Client::builder()
+            .user_agent("notoize")
+            .build()
+            .and_then(|client| client.head(url).send())
+            .is_ok_and(|res| res.status().is_success())
+    }
+
+    /// whether the file is in a local checkout of its repo at `{root}/{repo}`, for
+    /// [`Manifest::validate`]
+    pub fn exists_in(&self, root: &Path) -> bool {
+        root.join(&self.repo).join(&self.path).is_file()
+    }
+}
+
+/// where each family's font file lives upstream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub fonts: HashMap<FontFamily, FontSource>,
+}
+
+impl Manifest {
+    /// every family in the script table, where upstream's naming conventions put it: most families
+    /// in notofonts.github.io, CJK in noto-cjk and emoji in noto-emoji. this is a guess from the
+    /// family name, not read from upstream, so check it with [`Manifest::validate`] and
+    /// [`merge`](Self::merge) in overrides for files that don't follow the conventions
+    pub fn builtin() -> Self {
+        Self {
+            fonts: all_variants().into_iter().map(|f| (f.clone(), builtin_source(&f))).collect(),
+        }
+    }
+
+    /// reads a manifest saved as json, like one written by [`Manifest::to_json`]
+    /// # Errors
+    /// if the json isn't a manifest
+    pub fn from_json(json: &[u8]) -> serde_json::Result<Self> { serde_json::from_slice(json) }
+
+    /// # Panics
+    /// if the manifest can't be serialized (should never happen)
+    pub fn to_json(&self) -> String { serde_json::to_string_pretty(self).unwrap() }
+
+    /// replaces entries with those from another manifest, like user overrides
+    #[must_use]
+    pub fn merge(mut self, other: Self) -> Self {
+        self.fonts.extend(other.fonts);
+        self
+    }
+
+    /// where to get a family from. families missing from the manifest are guessed at the same way
+    /// as in [`Manifest::builtin`]
+    pub fn resolve(&self, family: &FontFamily) -> FontSource {
+        self.fonts.get(family).cloned().unwrap_or_else(|| builtin_source(family))
+    }
+
+    /// the file size of each font that has one, for [`NotoizeConfig::sizes`]
+    pub fn sizes(&self) -> HashMap<FontFamily, usize> {
+        self.fonts.iter().filter_map(|(f, s)| Some((f.clone(), s.size?))).collect()
+    }
+
+    /// resolves every family in the script table and returns the ones whose files `exists`
+    /// can't find, e.g. [`FontSource::exists_on_github`]
+    pub fn validate(&self, mut exists: impl FnMut(&FontSource) -> bool) -> Vec<FontFamily> {
+        all_variants()
+            .into_iter()
+            .filter(|f| {
+                cprint!("\x1b[92mchecking\x1b[m {f}");
+                !exists(&self.resolve(f))
+            })
+            .collect()
+    }
+}
+
+fn builtin_source(family: &FontFamily) -> FontSource {
+    let f = family.filename();
+    if let Some(region) = family.cjk_region() {
+        FontSource::new(
+            "notofonts",
+            "noto-cjk",
+            format!("{}/OTF/{}/{f}", family.0.split(' ').next().unwrap(), region.upstream_dir()),
+        )
+    } else if family.class() == DesignClass::Color {
+        FontSource::new("googlefonts", "noto-emoji", format!("fonts/{f}"))
+    } else {
+        FontSource::new(
+            "notofonts",
+            "notofonts.github.io",
+            format!("fonts/{}/hinted/ttf/{f}", family.postscript_prefix()),
+        )
+    }
+}
+
+impl Font {
+    /// retrieves one font file from github
+    /// # Panics
+    /// if the font isn't where the builtin manifest says (should never happen)
+    pub fn fetch(x: &FontFamily) -> Self { Self::fetch_from(x, &Manifest::builtin()) }
+
+    /// retrieves one font file from github, from where the manifest says
+    /// # Panics
+    /// if the font isn't where the manifest says
+    pub fn fetch_from(x: &FontFamily, manifest: &Manifest) -> Self {
+        Self::try_fetch_from(x, manifest).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// like [`fetch_from`](Self::fetch_from), for when upstream may have moved the file
+    /// # Errors
+    /// if the font isn't where the manifest says
+    pub fn try_fetch_from(x: &FontFamily, manifest: &Manifest) -> Result<Self, String> {
+        let source = manifest.resolve(x);
+        cprint!("\x1b[92mfetching\x1b[m {x}");
+        let bytes = source.fetch().map_err(|e| {
+            format!(
+                "could not find \x1b[91m{x}\x1b[m at {}/{}/{}. The err from gh-file-curler \
+                 is:\n    {e}",
+                source.owner, source.repo, source.path
+            )
+        })?;
+        Ok(Font { filename: source.filename().to_string(), fontname: x.clone(), bytes })
+    }
+
+    /// whether the font has the `vert` or `vrt2` feature for vertical text
+    pub fn has_vertical_features(&self) -> bool {
+        ttf_parser::Face::parse(&self.bytes, 0).is_ok_and(|face| {
+            face.tables().gsub.is_some_and(|gsub| {
+                gsub.features.into_iter().any(|f| [b"vert", b"vrt2"].contains(&&f.tag.to_bytes()))
+            })
+        })
+    }
+
+    /// the font's vertical metrics, or `None` if it can't be parsed
+    pub fn metrics(&self) -> Option<Metrics> {
+        let face = ttf_parser::Face::parse(&self.bytes, 0).ok()?;
+        let em = f64::from(face.units_per_em());
+        Some(Metrics {
+            ascent: f64::from(face.ascender()) / em,
+            descent: -f64::from(face.descender()) / em,
+            x_height: face.x_height().filter(|x| *x > 0).map(|x| f64::from(x) / em),
+        })
+    }
+
+    /// the font with only the glyphs for the codepoints and whatever GSUB can turn them into, so
+    /// shaping still works. `cmap` only maps the codepoints, and GSUB, GPOS and GDEF only have
+    /// the rules for the glyphs that are left. glyph ids don't change
+    /// # Errors
+    /// if the font can't be parsed, or has outlines the subsetter can't handle (like the color
+    /// bitmaps in Noto Color Emoji)
+    pub fn subset(&self, codepoints: &[u32]) -> Result<Subset, String> {
+        let face = ttf_parser::Face::parse(&self.bytes, 0).map_err(|e| e.to_string())?;
+        let glyphs = glyph_closure(&face, codepoints).into_iter().collect_vec();
+        let stripped = subsetter::subset(&self.bytes, 0, subsetter::Profile::pdf(&glyphs))
+            .map_err(|e| e.to_string())?;
+        let glyph_set = glyphs.iter().copied().collect();
+        let mut tables = subset::layout_tables(&self.bytes, &glyph_set)?;
+        tables.push((*b"cmap", subset::cmap(&self.bytes, codepoints)?));
+        Ok(Subset {
+            font: Font {
+                filename: self.filename.clone(),
+                fontname: self.fontname.clone(),
+                bytes: with_tables(&stripped, tables)?,
+            },
+            glyphs: glyphs.len(),
+        })
+    }
+}
+
+/// a terminal emulator to configure fallback fonts for, see [`FontStack::terminal_config`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    /// `font_family` and `symbol_map` lines for kitty.conf
+    Kitty,
+    /// a `wezterm.font_with_fallback` for wezterm.lua
+    WezTerm,
+    /// the `[font]` table for alacritty.toml. alacritty has no fallback list of its own and goes
+    /// by fontconfig for the rest, see [`FontStack::fontconfig`]
+    Alacritty,
+    /// the `font` line for foot.ini
+    Foot,
+    /// one family per line, in fallback order
+    Plain,
+}
+
+/// how a font file is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// plain TTF or OTF, as upstream has them
+    #[default]
+    Sfnt,
+    /// zlib-compressed tables
+    Woff,
+    /// brotli-compressed, with the glyf table transformed
+    Woff2,
+}
+
+impl Font {
+    /// the font in another encoding, with the filename extension to match
+    /// # Errors
+    /// if the font can't be parsed
+    pub fn encode(&self, encoding: Encoding) -> Result<Self, String> {
+        let sfnt = self.decode()?;
+        let (stem, _) = sfnt.filename.rsplit_once('.').unwrap_or((&sfnt.filename, ""));
+        let (bytes, ext) = match encoding {
+            Encoding::Sfnt => return Ok(sfnt),
+            Encoding::Woff => (woff::encode_woff(&sfnt.bytes)?, "woff"),
+            Encoding::Woff2 => (woff::encode_woff2(&sfnt.bytes)?, "woff2"),
+        };
+        Ok(Self { filename: format!("{stem}.{ext}"), fontname: sfnt.fontname, bytes })
+    }
+
+    /// the plain TTF or OTF font of a WOFF or WOFF2 file, or the font as is if it already is one
+    /// # Errors
+    /// if the WOFF or WOFF2 file is malformed
+    pub fn decode(&self) -> Result<Self, String> {
+        if !matches!(self.bytes.get(..4), Some(b"wOFF" | b"wOF2")) {
+            return Ok(self.clone());
+        }
+        let bytes = woff::decode(&self.bytes)?;
+        let ext = if bytes.starts_with(b"OTTO") { "otf" } else { "ttf" };
+        let (stem, _) = self.filename.rsplit_once('.').unwrap_or((&self.filename, ""));
+        Ok(Self { filename: format!("{stem}.{ext}"), fontname: self.fontname.clone(), bytes })
+    }
+}
+
+/// one part of a font, from [`FontStack::slices`]
+#[derive(Debug, Clone)]
+pub struct Slice {
+    pub font: Font,
+    /// the codepoints the slice is for, in order. it also has glyphs for the shaping support
+    /// characters, which aren't in here
+    pub codepoints: Vec<u32>,
+}
+
+/// a font cut down to some codepoints, from [`Font::subset`]
+#[derive(Debug, Clone)]
+pub struct Subset {
+    pub font: Font,
+    /// how many glyphs are left, not counting the components of composite glyphs
+    pub glyphs: usize,
+}
+
+/// the glyphs for the codepoints, `.notdef`, and everything GSUB can substitute them with
+fn glyph_closure(face: &ttf_parser::Face, codepoints: &[u32]) -> BTreeSet<u16> {
+    let mut glyphs = codepoints
+        .iter()
+        .filter_map(|c| face.glyph_index(char::from_u32(*c)?))
+        .map(|g| g.0)
+        .chain([0])
+        .collect::<BTreeSet<_>>();
+    let Some(gsub) = face.tables().gsub else { return glyphs };
+    // contextual lookups only point at other lookups, which get applied to every glyph anyway
+    loop {
+        let before = glyphs.len();
+        for lookup in gsub.lookups {
+            for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+                let new = substitutes(&subtable, &glyphs);
+                glyphs.extend(new);
+            }
+        }
+        if glyphs.len() == before {
+            return glyphs;
+        }
+    }
+}
+
+/// the glyphs a GSUB subtable can substitute some glyphs with
+fn substitutes(subtable: &SubstitutionSubtable, glyphs: &BTreeSet<u16>) -> Vec<u16> {
+    let covered = |coverage: ttf_parser::opentype_layout::Coverage| {
+        glyphs.iter().filter_map(move |g| coverage.get(GlyphId(*g))).collect_vec()
+    };
+    let ids = match subtable {
+        SubstitutionSubtable::Single(SingleSubstitution::Format1 { coverage, delta }) => {
+            return glyphs
+                .iter()
+                .filter(|g| coverage.contains(GlyphId(**g)))
+                .map(|g| g.wrapping_add_signed(*delta))
+                .collect();
+        }
+        SubstitutionSubtable::Single(SingleSubstitution::Format2 { coverage, substitutes }) => {
+            covered(*coverage).into_iter().filter_map(|i| substitutes.get(i)).collect_vec()
+        }
+        SubstitutionSubtable::Multiple(s) => covered(s.coverage)
+            .into_iter()
+            .filter_map(|i| s.sequences.get(i))
+            .flat_map(|seq| seq.substitutes)
+            .collect(),
+        SubstitutionSubtable::Alternate(s) => covered(s.coverage)
+            .into_iter()
+            .filter_map(|i| s.alternate_sets.get(i))
+            .flat_map(|set| set.alternates)
+            .collect(),
+        SubstitutionSubtable::Ligature(s) => covered(s.coverage)
+            .into_iter()
+            .filter_map(|i| s.ligature_sets.get(i))
+            .flatten()
+            .filter(|l| l.components.into_iter().all(|c| glyphs.contains(&c.0)))
+            .map(|l| l.glyph)
+            .collect(),
+        SubstitutionSubtable::ReverseChainSingle(s) => {
+            covered(s.coverage).into_iter().filter_map(|i| s.substitutes.get(i)).collect()
+        }
+        SubstitutionSubtable::Context(_) | SubstitutionSubtable::ChainContext(_) => vec![],
+    };
+    ids.into_iter().map(|g| g.0).collect()
+}
+
+/// the font with some more tables, replacing its own with the same tags
+fn with_tables(
+    font: &[u8],
+    extra: impl IntoIterator<Item = ([u8; 4], Vec<u8>)>,
+) -> Result<Vec<u8>, String> {
+    let raw = ttf_parser::RawFace::parse(font, 0).map_err(|e| e.to_string())?;
+    let tables = extra
+        .into_iter()
+        .chain(
+            raw.table_records
+                .into_iter()
+                .filter_map(|r| Some((r.tag.to_bytes(), raw.table(r.tag)?.to_vec()))),
+        )
+        .collect_vec();
+    build_sfnt(font[..4].try_into().unwrap(), tables)
+}
+
+/// an sfnt with the tables sorted by tag (the first of each), padded and checksummed
+/// # Errors
+/// if there are no tables, more than the table directory can hold, or more than 4 GiB of them
+fn build_sfnt(flavor: [u8; 4], mut tables: Vec<([u8; 4], Vec<u8>)>) -> Result<Vec<u8>, String> {
+    fn checksum(data: &[u8]) -> u32 {
+        data.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        })
+    }
+    tables.sort_by_key(|(tag, _)| *tag);
+    tables.dedup_by_key(|(tag, _)| *tag);
+    // the search range is 16 times the count rounded down to a power of two, in a u16
+    let count = u16::try_from(tables.len())
+        .ok()
+        .filter(|n| *n < 0x1000)
+        .ok_or("too many tables for an sfnt")?;
+    let selector = count.checked_ilog2().ok_or("an sfnt needs at least one table")? as u16;
+    let range = 16 << selector;
+    let mut out = flavor.to_vec();
+    for x in [count, range, selector, count * 16 - range] {
+        out.extend(x.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    let mut head = None;
+    for (tag, data) in &mut tables {
+        if tag == b"head" && data.len() >= 12 {
+            // the checksum adjustment is left out of every checksum
+            data[8..12].fill(0);
+            head = Some(offset);
+        }
+        out.extend(*tag);
+        out.extend(checksum(data).to_be_bytes());
+        let too_large = |_| "tables too large for an sfnt";
+        out.extend(u32::try_from(offset).map_err(too_large)?.to_be_bytes());
+        out.extend(u32::try_from(data.len()).map_err(too_large)?.to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in &tables {
+        out.extend(data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    if let Some(head) = head {
+        let adjustment = 0xb1b0_afba_u32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    Ok(out)
+}
+
+/// vertical metrics in ems
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub ascent: f64,
+    /// positive below the baseline
+    pub descent: f64,
+    pub x_height: Option<f64>,
+}
+
 impl FontStack {
     /// retrieves the font files from github
     /// # Panics
     /// if the font somehow doesn't exist (should never happen)
-    pub fn files(&self) -> Vec<Font> {
-        let res = self
-            .names
+    pub fn files(&self) -> Vec<Font> { self.files_from(&Manifest::builtin()) }
+
+    /// retrieves the font files from github, as WOFF or WOFF2 if you want
+    /// # Panics
+    /// if a font can't be encoded (should never happen)
+    pub fn files_as(&self, encoding: Encoding) -> Vec<Font> {
+        self.files().iter().map(|f| f.encode(encoding).unwrap()).collect()
+    }
+
+    /// retrieves the font files from where the manifest says
+    /// # Panics
+    /// if a font isn't where the manifest says
+    pub fn files_from(&self, manifest: &Manifest) -> Vec<Font> {
+        self.try_files_from(manifest).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// like [`files_from`](Self::files_from), for when upstream may have moved a file
+    /// # Errors
+    /// if a font isn't where the manifest says
+    pub fn try_files_from(&self, manifest: &Manifest) -> Result<Vec<Font>, String> {
+        let res = self.names.iter().map(|x| Font::try_fetch_from(x, manifest)).try_collect()?;
+        cprint!("retrieved font files");
+        Ok(res)
+    }
+
+    /// the codepoints each font ends up rendering, going through the stack in order like a
+    /// browser would
+    pub fn assigned(&self) -> Vec<(FontFamily, Vec<u32>)> {
+        let mut res = self.names.iter().map(|n| (n.clone(), vec![])).collect_vec();
+        for c in &self.codepoints {
+            let Some(fonts) = self.map.get(c) else { continue };
+            if let Some((_, cps)) = res.iter_mut().find(|(n, _)| fonts.contains(n)) {
+                cps.push(*c);
+            }
+        }
+        res
+    }
+
+    /// the shaping support characters of a font's script that it has, going by the map or its
+    /// file. they belong in its subset even where an earlier font renders them, since the shaper
+    /// only looks in the font of the run
+    fn own_shaping_support(&self, f: &FontFamily, file: Option<&Font>) -> Vec<u32> {
+        let face = file.and_then(|x| ttf_parser::Face::parse(&x.bytes, 0).ok());
+        shaping_support(&f.script())
             .iter()
-            .map(|x| {
-                let f = if x.contains("CJK") {
-                    let words = x.split_ascii_whitespace().collect_vec();
-                    format!("Noto{}CJK{}-Regular.otf", words[1], words[3].to_lowercase())
-                } else if SPECIAL_NAMES.contains(&x.as_str()) {
-                    SPECIAL_FILENAMES
-                        .iter()
-                        .find(|(name, _)| *name == x.as_str())
-                        .map(|(_, filename)| (*filename).to_string())
-                        .unwrap()
-                } else {
-                    format!("{}-Regular.ttf", x.replace([' ', '-'], ""))
+            .copied()
+            .filter(|c| {
+                self.map.get(c).is_some_and(|fonts| fonts.contains(f))
+                    || face.as_ref().is_some_and(|face| {
+                        char::from_u32(*c).and_then(|c| face.glyph_index(c)).is_some()
+                    })
+            })
+            .collect()
+    }
+
+    /// [`assigned`](Self::assigned) plus each font's own shaping support characters
+    fn assigned_with_shaping_support(&self, files: &[Font]) -> Vec<(FontFamily, Vec<u32>)> {
+        self.assigned()
+            .into_iter()
+            .map(|(f, cps)| {
+                let support = self.own_shaping_support(&f, files.iter().find(|x| x.fontname == f));
+                let cps = cps.into_iter().chain(support).sorted().dedup().collect();
+                (f, cps)
+            })
+            .collect()
+    }
+
+    /// which fonts render enough of the text, weighted by codepoint frequency, to be critical
+    pub fn critical_fonts(
+        &self,
+        frequencies: &HashMap<u32, usize>,
+        config: &NotoizeConfig,
+    ) -> Vec<FontFamily> {
+        let total = frequencies.values().sum::<usize>().max(1);
+        self.assigned()
+            .into_iter()
+            .filter(|(_, cps)| {
+                let n = cps.iter().filter_map(|c| frequencies.get(c)).sum::<usize>();
+                n as f64 / total as f64 >= config.critical_share || n >= config.critical_count
+            })
+            .map(|(f, _)| f)
+            .collect()
+    }
+
+    /// `<link rel=preload>` tags for the `files` of the critical fonts, at `base_url`
+    pub fn preload_links(&self, base_url: &str, files: &[Font]) -> String {
+        files
+            .iter()
+            .filter(|f| self.critical.contains(&f.fontname))
+            .map(|f| {
+                let file = &f.filename;
+                let format = match file.rsplit('.').next() {
+                    Some("woff2") => "woff2",
+                    Some("woff") => "woff",
+                    Some("otf") => "otf",
+                    _ => "ttf",
                 };
-                cprint!("\x1b[92mfetching\x1b[m {x}");
-                Font {
-                    filename: f.clone(),
-                    fontname: x.clone(),
-                    bytes: {
-                        let path = format!("fonts/{}/hinted/ttf/{f}", f.split('-').next().unwrap());
-                        wrapped_first(fetch("notofonts", "notofonts.github.io", &[&path]))
-                    }
-                    .unwrap_or_else(|e| {
-                        if x.contains("CJK") {
-                            let words = x.split_ascii_whitespace().collect_vec();
-                            wrapped_first(fetch("notofonts", "noto-cjk", &[&format!(
-                                "{}/OTF/{}/{f}",
-                                words[1],
-                                {
-                                    let var = words[3].to_lowercase();
-                                    match var.as_str() {
-                                        "jp" => "Japanese",
-                                        "kr" => "Korean",
-                                        "sc" => "SimplifiedChinese",
-                                        "tc" => "TraditionalChinese",
-                                        "hk" => "TraditionalChineseHK",
-                                        _ => {
-                                            panic!("unknown CJK variety `\x1b[91m{var}\x1b[m`")
-                                        }
-                                    }
-                                }
-                            )]))
-                            .unwrap()
-                        } else if x.contains("Emoji") {
-                            wrapped_first(fetch("googlefonts", "noto-emoji", &[
-                                "fonts/NotoColorEmoji.ttf",
-                            ]))
-                            .unwrap()
-                        } else {
-                            panic!(
-                                "could not find \x1b[91m{x}\x1b[m. The err from gh-file-curler \
-                                 is:\n    {e}"
-                            );
-                        }
-                    }),
-                }
+                format!(
+                    "<link rel=\"preload\" href=\"{base_url}{file}\" as=\"font\" \
+                     type=\"font/{format}\" crossorigin>\n"
+                )
             })
-            .collect();
-        cprint!("retrieved font files");
+            .collect()
+    }
+
+    /// the files cut down to the codepoints each font renders and the shaping support
+    /// characters of its script, see [`Font::subset`]. fonts that can't be subset are left whole
+    pub fn subset_files(&self, files: &[Font]) -> Vec<Subset> {
+        let assigned = self.assigned_with_shaping_support(files);
+        files
+            .iter()
+            .map(|f| {
+                let cps = assigned.iter().find(|(n, _)| *n == f.fontname).map_or(&[][..], |a| &a.1);
+                cprint!("\x1b[92msubsetting\x1b[m {}", f.fontname);
+                f.subset(cps).unwrap_or_else(|_| Subset {
+                    font: f.clone(),
+                    glyphs: ttf_parser::Face::parse(&f.bytes, 0)
+                        .map_or(0, |face| face.number_of_glyphs().into()),
+                })
+            })
+            .collect()
+    }
+
+    /// the `font-family` value for the stack, in fallback order
+    pub fn font_family(&self) -> String {
+        self.names.iter().map(|f| format!("\"{}\"", f.css_name())).join(", ")
+    }
+
+    /// a stylesheet with an `@font-face` for each font, with the files at `base_url` and a
+    /// `unicode-range` of the codepoints it renders, and the stack in a `--notoize` custom
+    /// property. given the `files()`, every font also gets the line height of the first one and
+    /// its x-height if both have one
+    pub fn stylesheet(&self, base_url: &str, files: &[Font]) -> String {
+        let faces = self.assigned().into_iter().map(|(f, cps)| (filename_in(files, &f), f, cps));
+        self.css(base_url, files, faces)
+    }
+
+    /// the files split into subset slices of up to `size` codepoints each, like google fonts
+    /// does, so browsers only download the slices a page uses. with `frequencies`, the most
+    /// frequent codepoints go in the first slices, otherwise they're in order. every slice also
+    /// has the glyphs for the shaping support characters of the font's script, but they're left
+    /// out of its codepoints so the ranges don't overlap. fonts that can't be subset are left
+    /// whole
+    pub fn slices(
+        &self,
+        files: &[Font],
+        size: usize,
+        frequencies: Option<&HashMap<u32, usize>>,
+    ) -> Vec<Slice> {
+        let mut res = vec![];
+        for (f, cps) in self.assigned() {
+            let Some(file) = files.iter().find(|x| x.fontname == f) else { continue };
+            let support = self.own_shaping_support(&f, Some(file));
+            let cps = match frequencies {
+                Some(freqs) => cps
+                    .into_iter()
+                    .sorted_by_key(|c| std::cmp::Reverse(freqs.get(c).copied().unwrap_or(0)))
+                    .collect_vec(),
+                None => cps,
+            };
+            let (stem, ext) = file.filename.rsplit_once('.').unwrap_or((&file.filename, "ttf"));
+            // if any slice fails, the font goes whole instead of next to the slices before it
+            let slices = cps
+                .chunks(size.max(1))
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let chunk = chunk.iter().copied().sorted().collect_vec();
+                    let glyphs = chunk.iter().chain(&support).copied().sorted().dedup();
+                    cprint!("\x1b[92mslicing\x1b[m {f} {i}");
+                    let subset = file.subset(&glyphs.collect_vec())?;
+                    Ok(Slice {
+                        font: Font { filename: format!("{stem}.{i}.{ext}"), ..subset.font },
+                        codepoints: chunk,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>();
+            match slices {
+                Ok(slices) => res.extend(slices),
+                Err(_) => {
+                    res.push(Slice {
+                        font: file.clone(),
+                        codepoints: cps.into_iter().sorted().collect(),
+                    });
+                }
+            }
+        }
         res
     }
 
-    pub fn map_string(&self) -> MapString {
-        fn stringify(stuff: &[String]) -> String {
-            stuff
-                .iter()
-                .sorted_by_cached_key(|f| script(f).0.to_lowercase())
-                .group_by(|f| script(f).0.to_lowercase())
+    /// like [`stylesheet`](Self::stylesheet), with an `@font-face` for each slice
+    pub fn sliced_stylesheet(&self, base_url: &str, slices: &[Slice]) -> String {
+        let files =
+            slices.iter().map(|s| &s.font).unique_by(|f| &f.fontname).cloned().collect_vec();
+        let faces = slices
+            .iter()
+            .map(|s| (s.font.filename.clone(), s.font.fontname.clone(), s.codepoints.clone()));
+        self.css(base_url, &files, faces)
+    }
+
+    /// `@font-face`s for some files, each with the family it's of and the codepoints it renders
+    fn css(
+        &self,
+        base_url: &str,
+        files: &[Font],
+        faces: impl IntoIterator<Item = (String, FontFamily, Vec<u32>)>,
+    ) -> String {
+        let metrics =
+            |f: &FontFamily| files.iter().find(|x| x.fontname == *f).and_then(Font::metrics);
+        let primary = self.names.first().and_then(metrics);
+        let mut css = String::new();
+        for (file, f, cps) in faces {
+            let format = match file.rsplit('.').next() {
+                Some("woff2") => "woff2",
+                Some("woff") => "woff",
+                Some("otf") => "opentype",
+                _ => "truetype",
+            };
+            let _ = write!(
+                css,
+                "@font-face {{\n  font-family: \"{}\";\n  src: url(\"{base_url}{file}\") \
+                 format(\"{format}\");\n",
+                f.css_name()
+            );
+            if !cps.is_empty() {
+                let _ = writeln!(css, "  unicode-range: {};", unicode_range(&cps));
+            }
+            if let (Some(p), Some(m)) = (primary, metrics(&f)) {
+                let adjust = p.x_height.zip(m.x_height).map_or(1.0, |(p, m)| p / m);
+                if adjust != 1.0 {
+                    let _ = writeln!(css, "  size-adjust: {:.2}%;", adjust * 100.0);
+                }
+                let _ = write!(
+                    css,
+                    "  ascent-override: {:.2}%;\n  descent-override: {:.2}%;\n",
+                    p.ascent / adjust * 100.0,
+                    p.descent / adjust * 100.0
+                );
+            }
+            css += "}\n";
+        }
+        let _ = writeln!(css, ":root {{\n  --notoize: {};\n}}", self.font_family());
+        css
+    }
+
+    /// a fontconfig `fonts.conf` that adds the fonts in `font_dir` and puts the stack behind the
+    /// `sans-serif`, `serif` and `monospace` aliases, with the families of each style preferred
+    /// and the rest accepted. with `cjk_languages`, the CJK fonts in the stack also get preferred
+    /// for the languages of their regions
+    pub fn fontconfig(&self, font_dir: &str, cjk_languages: bool) -> String {
+        let mut conf = format!(
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE fontconfig SYSTEM \
+             \"urn:fontconfig:fonts.dtd\">\n<fontconfig>\n  <dir>{}</dir>\n",
+            font_dir.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        );
+        let family = |f: &FontFamily| format!("      <family>{}</family>\n", f.css_name());
+        for alias in ["sans-serif", "serif", "monospace"] {
+            let (prefer, accept): (Vec<_>, Vec<_>) =
+                self.names.iter().partition(|f| f.generic_family() == alias);
+            let _ = write!(conf, "  <alias>\n    <family>{alias}</family>\n");
+            for (tag, fonts) in [("prefer", prefer), ("accept", accept)] {
+                if !fonts.is_empty() {
+                    let families = fonts.into_iter().map(family).collect::<String>();
+                    let _ = write!(conf, "    <{tag}>\n{families}    </{tag}>\n");
+                }
+            }
+            conf += "  </alias>\n";
+        }
+        if cjk_languages {
+            for f in &self.names {
+                let Some(region) = f.cjk_region() else { continue };
+                for lang in region.fontconfig_langs() {
+                    let _ = write!(
+                        conf,
+                        "  <match target=\"pattern\">\n    <test name=\"lang\" \
+                         compare=\"contains\"><string>{lang}</string></test>\n    <edit \
+                         name=\"family\" mode=\"prepend\" \
+                         binding=\"strong\"><string>{}</string></edit>\n  </match>\n",
+                        f.css_name()
+                    );
+                }
+            }
+        }
+        conf + "</fontconfig>\n"
+    }
+
+    /// the stack with each family swapped for its script's Mono family if there is one, like
+    /// Sans Mono for Sans, and the codepoints the family renders
+    pub fn monospaced(&self) -> Vec<(FontFamily, Vec<u32>)> {
+        let mut res = Vec::<(FontFamily, Vec<u32>)>::new();
+        for (f, cps) in self.assigned() {
+            let mono = f
+                .script()
+                .families()
                 .into_iter()
-                .map(|(_, mut g)| g.join(", "))
-                .join("\n    ")
+                .find(|v| v.qualifiers().contains(&Qualifier::Mono) && v.class() == f.class())
+                .unwrap_or(f);
+            match res.iter_mut().find(|(x, _)| *x == mono) {
+                Some((_, x)) => x.extend(cps),
+                None => res.push((mono, cps)),
+            }
+        }
+        for (_, cps) in &mut res {
+            cps.sort_unstable();
+        }
+        res
+    }
+
+    /// fallback font configuration for a terminal emulator, with [`monospaced`](Self::monospaced)
+    /// families
+    pub fn terminal_config(&self, terminal: Terminal) -> String {
+        let fonts = self.monospaced();
+        let names = fonts.iter().map(|(f, _)| f.css_name()).collect_vec();
+        match terminal {
+            Terminal::Kitty => {
+                let mut conf =
+                    names.first().map_or_else(String::new, |f| format!("font_family {f}\n"));
+                for (f, cps) in fonts.iter().skip(1).filter(|(_, cps)| !cps.is_empty()) {
+                    let ranges = ranges(cps)
+                        .into_iter()
+                        .map(|(start, end)| {
+                            if start == end {
+                                format!("U+{start:04X}")
+                            } else {
+                                format!("U+{start:04X}-U+{end:04X}")
+                            }
+                        })
+                        .join(",");
+                    let _ = writeln!(conf, "symbol_map {ranges} {}", f.css_name());
+                }
+                conf
+            }
+            Terminal::WezTerm => format!(
+                "config.font = wezterm.font_with_fallback {{\n{}}}\n",
+                names.iter().map(|f| format!("  '{f}',\n")).collect::<String>()
+            ),
+            Terminal::Alacritty => names
+                .first()
+                .map_or_else(String::new, |f| format!("[font]\nnormal = {{ family = \"{f}\" }}\n")),
+            Terminal::Foot => format!("[main]\nfont={}\n", names.join(",")),
+            Terminal::Plain => names.iter().map(|f| format!("{f}\n")).collect(),
+        }
+    }
+
+    /// a typst `#set text` rule with the stack as the font fallback list
+    pub fn typst(&self) -> String {
+        format!(
+            "#set text(font: ({}))\n",
+            self.names.iter().map(|f| format!("\"{}\"", f.css_name())).join(", ")
+        )
+    }
+
+    /// a LaTeX preamble that loads the `files` (as written, in whatever encoding) from
+    /// `font_dir` with fontspec, with a `\newfontfamily` for each font. the first font is the
+    /// main one, and the rest are its fallback with luaotfload on LuaLaTeX, or switched to for
+    /// the Unicode blocks they render most of with ucharclasses on XeLaTeX
+    pub fn latex(&self, font_dir: &str, files: &[Font]) -> String {
+        let dir =
+            if font_dir.ends_with('/') { font_dir.to_string() } else { format!("{font_dir}/") };
+        let mut tex = "\\usepackage{fontspec}\n\\usepackage{iftex}\n".to_string();
+        for f in &self.names {
+            let _ = writeln!(
+                tex,
+                "\\newfontfamily{}{{{}}}[Path={dir}]",
+                latex_command(f),
+                filename_in(files, f)
+            );
+        }
+        let Some((main, rest)) = self.names.split_first() else { return tex };
+        let fallbacks = rest
+            .iter()
+            .map(|f| format!("    \"[{dir}{}]:mode=harf;\",\n", filename_in(files, f)))
+            .collect::<String>();
+        let _ = write!(
+            tex,
+            "\\ifluatex\n  \\directlua{{luaotfload.add_fallback(\"notoize\", {{\n{fallbacks}  \
+             }})}}\n  \\setmainfont{{{}}}[Path={dir}, RawFeature={{fallback=notoize}}]\n\\else\n  \
+             \\setmainfont{{{0}}}[Path={dir}]\n  \\usepackage{{ucharclasses}}\n",
+            filename_in(files, main)
+        );
+        // the font that renders the most of each block
+        let blocks = self
+            .assigned()
+            .into_iter()
+            .flat_map(|(f, cps)| {
+                cps.into_iter()
+                    .filter_map(|c| unicode_blocks::find_unicode_block(char::from_u32(c)?))
+                    .map(move |b| (b, f.clone()))
+            })
+            .counts()
+            .into_iter()
+            .sorted_by_key(|((b, f), n)| {
+                (*b, std::cmp::Reverse(*n), self.names.iter().position(|x| x == f))
+            })
+            .dedup_by(|((a, _), _), ((b, _), _)| a == b)
+            .filter(|((_, f), _)| f != main);
+        for ((block, f), _) in blocks {
+            let class =
+                block.name().chars().filter(char::is_ascii_alphanumeric).collect::<String>();
+            let _ = writeln!(
+                tex,
+                "  \\setTransitionsFor{{{class}}}{{{}}}{{\\normalfont}}",
+                latex_command(&f)
+            );
+        }
+        tex + "\\fi\n"
+    }
+
+    /// the names of the fonts' `files` as written, like for copying them into `/system/fonts`
+    pub fn filenames(&self, files: &[Font]) -> Vec<String> {
+        self.names.iter().map(|f| filename_in(files, f)).collect()
+    }
+
+    /// an android `fonts.xml`. the first font and the first latin, greek and cyrillic font of each
+    /// other generic family are the `sans-serif`, `serif` and `monospace` families, and the rest
+    /// are fallbacks for the language of their CJK region or script, serif fonts only
+    /// `fallbackFor="serif"` where the language has another font. copy the `files` (or
+    /// `filenames(files)`) into `/system/fonts`
+    pub fn android_fonts_xml(&self, files: &[Font]) -> String {
+        let font = |f: &FontFamily, serif: bool| {
+            format!(
+                "        <font weight=\"400\" style=\"normal\"{}>{}</font>\n",
+                if serif { " fallbackFor=\"serif\"" } else { "" },
+                filename_in(files, f)
+            )
+        };
+        let lang = |f: &FontFamily| match f.cjk_region() {
+            Some(region) => Some(region.lang().to_string()),
+            None => match f.script().iso15924() {
+                "Zyyy" | "Zinh" | "Zzzz" => None,
+                code => Some(format!("und-{code}")),
+            },
+        };
+        let mut xml =
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<familyset version=\"23\">\n".to_string();
+        let mut named = HashSet::new();
+        let mut fallbacks = Vec::<(Option<String>, Vec<&FontFamily>)>::new();
+        for (i, f) in self.names.iter().enumerate() {
+            if (i == 0 || f.script() == Script::LatinGreekCyrillic)
+                && named.insert(f.generic_family())
+            {
+                let _ = write!(
+                    xml,
+                    "    <family name=\"{}\">\n{}    </family>\n",
+                    f.generic_family(),
+                    font(f, false)
+                );
+            } else {
+                let lang = lang(f);
+                match fallbacks.iter_mut().find(|(l, _)| *l == lang) {
+                    Some((_, fonts)) => fonts.push(f),
+                    None => fallbacks.push((lang, vec![f])),
+                }
+            }
+        }
+        for (lang, fonts) in fallbacks {
+            let (serif, other): (Vec<_>, Vec<_>) =
+                fonts.into_iter().partition(|f| f.class() == DesignClass::Serif);
+            let fonts = other
+                .iter()
+                .map(|f| font(f, false))
+                .chain(serif.iter().map(|f| font(f, !other.is_empty())))
+                .collect::<String>();
+            let lang = lang.map_or_else(String::new, |l| format!(" lang=\"{l}\""));
+            let _ = write!(xml, "    <family{lang}>\n{fonts}    </family>\n");
+        }
+        xml + "</familyset>\n"
+    }
+
+    /// what supports each covered codepoint, in order
+    pub fn report(&self) -> Vec<CodepointReport> {
+        self.map
+            .iter()
+            .filter(|m| !m.1.is_empty())
+            .sorted()
+            .map(|(c, fonts)| CodepointReport {
+                codepoint: *c,
+                scripts: ScriptGroup::group(fonts),
+                conflict: scripts(fonts).len() > 1,
+                missing: missing_variants(fonts),
+            })
+            .collect()
+    }
+
+    /// the [`report`](Self::report) as text
+    pub fn map_string(&self) -> MapString {
+        fn stringify(groups: &[ScriptGroup]) -> String {
+            groups.iter().map(|g| g.families.iter().map(FontFamily::name).join(", ")).join("\n    ")
         }
         let mut all = String::new();
         let mut conflicts = String::new();
         let mut missing = String::new();
-        for (c, fonts) in
-            self.map.iter().filter(|m| !m.1.is_empty()).collect_vec().into_iter().sorted()
-        {
-            let fonts_str = stringify(fonts);
-            let entry = &format!("{c:04x}\n    {fonts_str}\n");
+        for r in self.report() {
+            let entry = &format!("{:04x}\n    {}\n", r.codepoint, stringify(&r.scripts));
             all += entry;
-            if scripts(fonts).len() > 1 {
+            if r.conflict {
                 conflicts += entry;
             }
-            let bad = missing_variants(fonts);
-            if !bad.is_empty() {
-                let _ = write!(missing, "{c:04x}\n    {}\n", stringify(&bad));
+            if !r.missing.is_empty() {
+                let bad = ScriptGroup::group(&r.missing);
+                let _ = write!(missing, "{:04x}\n    {}\n", r.codepoint, stringify(&bad));
             }
         }
         MapString { all, conflicts, missing }
     }
 }
 
+#[cfg(feature = "fontdb")]
+impl FontStack {
+    /// loads the `files()` into a fontdb database, returning the face IDs of each family. the
+    /// database's `sans-serif`, `serif` and `monospace` become the first font in the stack of
+    /// each generic family
+    pub fn load_fontdb(
+        &self,
+        files: &[Font],
+        db: &mut fontdb::Database,
+    ) -> HashMap<FontFamily, Vec<fontdb::ID>> {
+        for f in self.names.iter().unique_by(|f| f.generic_family()) {
+            match f.generic_family() {
+                "monospace" => db.set_monospace_family(f.css_name()),
+                "serif" => db.set_serif_family(f.css_name()),
+                _ => db.set_sans_serif_family(f.css_name()),
+            }
+        }
+        files
+            .iter()
+            .map(|f| {
+                let source = fontdb::Source::Binary(std::sync::Arc::new(f.bytes.clone()));
+                (f.fontname.clone(), db.load_font_source(source).to_vec())
+            })
+            .collect()
+    }
+}
+
+/// cosmic-text fallback in stack order, from [`FontStack::cosmic_fallback`]
+#[cfg(feature = "cosmic-text")]
+#[derive(Debug, Clone, Default)]
+pub struct NotoizeFallback {
+    common: Vec<&'static str>,
+    scripts: HashMap<UnicodeScriptValue, Vec<&'static str>>,
+}
+
+#[cfg(feature = "cosmic-text")]
+impl cosmic_text::Fallback for NotoizeFallback {
+    fn common_fallback(&self) -> &[&'static str] { &self.common }
+
+    fn forbidden_fallback(&self) -> &[&'static str] { &[] }
+
+    fn script_fallback(&self, script: UnicodeScriptValue, _locale: &str) -> &[&'static str] {
+        self.scripts.get(&script).map_or(&[], |s| s)
+    }
+}
+
+/// a family name that lives forever, leaked only the first time it's seen
+#[cfg(feature = "cosmic-text")]
+fn intern(name: String) -> &'static str {
+    static NAMES: LazyLock<std::sync::Mutex<HashSet<&'static str>>> =
+        LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(name) = names.get(name.as_str()) {
+        return name;
+    }
+    let name = &*Box::leak(name.into_boxed_str());
+    names.insert(name);
+    name
+}
+
+#[cfg(feature = "cosmic-text")]
+impl FontStack {
+    /// the whole stack as the common fallback, and for each Unicode script the fonts that render
+    /// its characters. cosmic-text wants `&'static str`s, so the family names are interned, each
+    /// leaked once however many times this is called
+    pub fn cosmic_fallback(&self) -> NotoizeFallback {
+        let common = self.names.iter().map(|f| intern(f.css_name())).collect_vec();
+        let mut scripts = HashMap::<_, Vec<_>>::new();
+        for (i, (_, cps)) in self.assigned().into_iter().enumerate() {
+            for script in cps.iter().filter_map(|c| char::from_u32(*c)).map(|c| c.script()).unique()
+            {
+                let fonts = scripts.entry(script).or_default();
+                if !fonts.contains(&common[i]) {
+                    fonts.push(common[i]);
+                }
+            }
+        }
+        NotoizeFallback { common, scripts }
+    }
+
+    /// a cosmic-text font system with just the `files()`, falling back in stack order
+    pub fn font_system(&self, files: &[Font], locale: String) -> cosmic_text::FontSystem {
+        let mut db = fontdb::Database::new();
+        self.load_fontdb(files, &mut db);
+        cosmic_text::FontSystem::new_with_locale_and_db_and_fallback(
+            locale,
+            db,
+            self.cosmic_fallback(),
+        )
+    }
+}
+
+#[cfg(feature = "egui")]
+impl FontStack {
+    /// the families that render something, each after the families that render codepoints it
+    /// also covers, otherwise in the order of `assigned`
+    fn fallback_order(&self, assigned: &[(FontFamily, Vec<u32>)]) -> Vec<FontFamily> {
+        let before = |p: &(FontFamily, Vec<u32>), f: &FontFamily| {
+            p.0 != *f && p.1.iter().any(|c| self.map.get(c).is_some_and(|m| m.contains(f)))
+        };
+        let mut left = assigned.iter().filter(|(_, cps)| !cps.is_empty()).collect_vec();
+        let mut order = vec![];
+        while !left.is_empty() {
+            let i =
+                (0..left.len()).find(|i| !left.iter().any(|p| before(p, &left[*i].0))).unwrap_or(0);
+            order.push(left.remove(i).0.clone());
+        }
+        order
+    }
+
+    /// egui font definitions with the `files()` in front of `base`'s proportional and monospace
+    /// chains, so egui's own fonts are only a last resort. monospace prefers the
+    /// [`monospaced`](Self::monospaced) families that are in `files`
+    pub fn egui_fonts(&self, files: &[Font], base: egui::FontDefinitions) -> egui::FontDefinitions {
+        let mut defs = base;
+        for f in files {
+            defs.font_data.insert(
+                f.fontname.css_name(),
+                std::sync::Arc::new(egui::FontData::from_owned(f.bytes.clone())),
+            );
+        }
+        let has = |f: &FontFamily| files.iter().any(|x| x.fontname == *f);
+        let proportional = self
+            .fallback_order(&self.assigned())
+            .into_iter()
+            .chain(self.names.iter().cloned())
+            .unique()
+            .collect_vec();
+        let monospace = self
+            .fallback_order(&self.monospaced().into_iter().filter(|(f, _)| has(f)).collect_vec())
+            .into_iter()
+            .chain(proportional.iter().cloned())
+            .unique()
+            .collect_vec();
+        for (family, fonts) in [
+            (egui::FontFamily::Proportional, proportional),
+            (egui::FontFamily::Monospace, monospace),
+        ] {
+            let chain = defs.families.entry(family).or_default();
+            let names = fonts.iter().filter(|f| has(f)).map(FontFamily::css_name).collect_vec();
+            chain.retain(|n| !names.contains(n));
+            chain.splice(0..0, names);
+        }
+        defs
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct BlockData {
     cps: HashMap<String, CodepointFontSupport>,
@@ -184,7 +1420,11 @@ struct BlockEndpoints {
 #[derive(Clone)]
 pub struct NotoizeClient {
     blocks: Vec<BlockEndpoints>,
-    font_support: HashMap<u32, Vec<String>>,
+    font_support: HashMap<u32, Vec<FontFamily>>,
+    /// whether each font that has been checked has `vert` or `vrt2`
+    vertical_features: HashMap<FontFamily, bool>,
+    /// where to fetch fonts from when the client needs them
+    manifest: Manifest,
 }
 
 impl Default for NotoizeClient {
@@ -205,16 +1445,138 @@ impl NotoizeClient {
                 .unwrap()
             },
             font_support: HashMap::new(),
+            vertical_features: HashMap::new(),
+            manifest: Manifest::default(),
         }
     }
 
+    /// fetch the fonts the client needs (like for [`NotoizeConfig::vertical`]) from where the
+    /// manifest says. families it doesn't have resolve like in [`Manifest::builtin`], which is
+    /// also the default
+    #[must_use]
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
     /// returns a minimal font stack for rendering the text
+    pub fn notoize(&mut self, text: &str) -> FontStack {
+        self.notoize_with(text, &NotoizeConfig::default())
+    }
+
+    /// returns a minimal font stack for rendering the text, with options
     /// # Panics
     /// if the block data can;t be found (should never happen)
-    pub fn notoize(&mut self, text: &str) -> FontStack {
-        let codepoints = text.chars().map(|c| c as u32).sorted().dedup().collect_vec();
-        let mut fonts = Vec::with_capacity(codepoints.len());
-        let mut data = BlockData { cps: HashMap::new(), fonts: None };
+    pub fn notoize_with(&mut self, text: &str, config: &NotoizeConfig) -> FontStack {
+        let Codepoints { codepoints, invisible, normalized } = config.codepoints(text);
+        self.load_blocks(&codepoints);
+        let historic = codepoints
+            .iter()
+            .filter(|c| {
+                config.historic == CharPolicy::Report
+                    && !self.font_support.get(c).is_none_or(Vec::is_empty)
+                    && self.candidates(**c, config).is_empty()
+            })
+            .copied()
+            .collect();
+        let (mut fonts, mut over_budget) = match &config.sizes {
+            Some(sizes) => self.select_by_size(&codepoints, config, sizes),
+            None => (self.select(&codepoints, config), vec![]),
+        };
+        let support = if config.shaping_support {
+            self.add_shaping_support(&mut fonts, config, &mut over_budget)
+        } else {
+            vec![]
+        };
+        let (no_vertical_features, unverified) =
+            if config.vertical { self.check_vertical_features(&fonts) } else { (vec![], vec![]) };
+        over_budget.sort_unstable();
+        over_budget.dedup();
+        cprint!("determined necessary fonts");
+        let mut stack = FontStack {
+            names: fonts,
+            map: self
+                .font_support
+                .iter()
+                .filter(|(c, _)| {
+                    config.char_policy(**c) == CharPolicy::Require || support.contains(c)
+                })
+                .map(|(c, f)| (*c, f.clone()))
+                .collect(),
+            invisible,
+            normalized,
+            over_budget,
+            historic,
+            no_vertical_features,
+            unverified,
+            codepoints: codepoints.into_iter().chain(support).sorted().dedup().collect(),
+            critical: vec![],
+        };
+        let frequencies =
+            config.frequencies.clone().unwrap_or_else(|| text.chars().map(|c| c as u32).counts());
+        stack.critical = stack.critical_fonts(&frequencies, config);
+        stack
+    }
+
+    /// finds the fonts that can render the whole text on their own
+    /// # Panics
+    /// if the block data can;t be found (should never happen)
+    pub fn single_font(&mut self, text: &str, config: &NotoizeConfig) -> SingleFont {
+        let codepoints = config.codepoints(text).codepoints;
+        self.load_blocks(&codepoints);
+        let coverage = codepoints
+            .iter()
+            .flat_map(|c| self.font_support.get(c).into_iter().flatten())
+            .counts()
+            .into_iter()
+            .sorted_by_key(|(f, n)| (std::cmp::Reverse(*n), config.preference(f)))
+            .collect_vec();
+        let full = coverage
+            .iter()
+            .take_while(|(_, n)| *n == codepoints.len())
+            .map(|(f, _)| (*f).clone())
+            .collect_vec();
+        if !full.is_empty() || codepoints.is_empty() {
+            return SingleFont::Full(full);
+        }
+        let Some((best, _)) = coverage.first() else { return SingleFont::Unsupported };
+        SingleFont::Partial {
+            font: (*best).clone(),
+            missing: codepoints
+                .iter()
+                .filter(|c| !self.font_support.get(c).is_some_and(|f| f.contains(best)))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// loads every block and lists the families the script table doesn't know about, so upstream
+    /// drift shows up before it reaches a stack
+    pub fn self_check(&mut self) -> Vec<FontFamily> {
+        self.coverage()
+            .into_keys()
+            .filter(|f| matches!(f.script(), Script::Unknown(_)))
+            .sorted()
+            .collect()
+    }
+
+    /// loads every block and returns the codepoints each family covers, in order
+    pub fn coverage(&mut self) -> HashMap<FontFamily, Vec<u32>> {
+        let starts = self.blocks.iter().map(|b| b.start).sorted().collect_vec();
+        self.load_blocks(&starts);
+        let mut coverage = HashMap::<FontFamily, Vec<u32>>::new();
+        for (c, fonts) in self.font_support.iter().sorted_by_key(|(c, _)| **c) {
+            for f in fonts {
+                coverage.entry(f.clone()).or_default().push(*c);
+            }
+        }
+        coverage
+    }
+
+    /// fetches the block data for every codepoint not already cached
+    /// # Panics
+    /// if the block data can;t be found (should never happen)
+    fn load_blocks(&mut self, codepoints: &[u32]) {
         let mut old_block = None;
         for (i, c) in codepoints.iter().enumerate() {
             // blocks can only start at u+xxxxx0
@@ -222,27 +1584,19 @@ impl NotoizeClient {
                 continue;
             }
             let block = self.blocks.iter().find(|b| b.start <= *c && *c <= b.end);
-            if block != old_block {
-                if let Some(i) = block.map(|b| b.ix) {
-                    let path = format!("blocks/block-{i:03}.json");
-                    let block = block.unwrap();
-                    let e = {
-                        if !self.font_support.contains_key(c) {
-                            cprint!(
-                                "\x1b[92mfetching\x1b[m {:04x}-{:04x} {}",
-                                block.start,
-                                block.end,
-                                block.name
-                            );
-                            data = serde_json::from_slice::<BlockData>(
-                                &fetch("notofonts", "overview", &[&path]).unwrap().0[0]
-                                    .content
-                                    .clone(),
-                            )
-                            .unwrap();
-                        }
-                        &data
-                    };
+            if block != old_block && !self.font_support.contains_key(c) {
+                if let Some(block) = block {
+                    let path = format!("blocks/block-{:03}.json", block.ix);
+                    cprint!(
+                        "\x1b[92mfetching\x1b[m {:04x}-{:04x} {}",
+                        block.start,
+                        block.end,
+                        block.name
+                    );
+                    let e = serde_json::from_slice::<BlockData>(
+                        &fetch("notofonts", "overview", &[&path]).unwrap().0[0].content.clone(),
+                    )
+                    .unwrap();
                     let formatted = e
                         .cps
                         .iter()
@@ -254,229 +1608,687 @@ impl NotoizeClient {
                                     .map_or_else(|| v.fonts.clone().unwrap_or(vec![]), Clone::clone)
                                     .iter()
                                     .filter(|f| !["UI", "Display"].iter().any(|a| f.contains(a)))
-                                    .filter(|f| {
-                                        script(f);
-                                        true
-                                    })
-                                    .cloned()
+                                    .map(|f| FontFamily::new(f))
                                     .collect_vec(),
                             )
                         })
                         .collect::<HashMap<_, _>>();
-                    let v = vec![];
                     for c in block.start..=block.end {
-                        let insert = formatted.get_key_value(&c).unwrap_or((&c, &v));
-                        self.font_support.insert(*insert.0, insert.1.clone());
+                        self.font_support.insert(c, formatted.get(&c).cloned().unwrap_or_default());
                     }
                 }
             }
             old_block = block;
         }
-        let font_support = &self.font_support;
+    }
+
+    /// the fonts supporting a codepoint, most preferred first
+    fn candidates(&self, c: u32, config: &NotoizeConfig) -> Vec<FontFamily> {
+        self.font_support
+            .get(&c)
+            .map(|f| {
+                f.iter()
+                    .filter(|e| {
+                        config.historic == CharPolicy::Require
+                            || e.script().status() != ScriptStatus::Excluded
+                    })
+                    .cloned()
+                    .sorted_by_key(|e| config.preference(e))
+                    .collect_vec()
+            })
+            .unwrap_or_default()
+    }
+
+    /// picks the preferred font for each codepoint
+    fn select(&self, codepoints: &[u32], config: &NotoizeConfig) -> Vec<FontFamily> {
+        let mut fonts = Vec::with_capacity(codepoints.len());
         for (c, f) in codepoints
             .iter()
-            .filter(|c| !font_support.get(c).unwrap_or(&vec![]).is_empty())
-            .map(|c| {
-                (
-                    c,
-                    font_support
-                        .get(c)
-                        .unwrap()
-                        .iter()
-                        .map(ToString::to_string)
-                        .sorted_by_key(|e| (!e.contains("Sans"), e.clone()))
-                        .collect_vec(),
-                )
-            })
+            .map(|c| (c, self.candidates(*c, config)))
             .filter(|(_, f)| !f.is_empty())
         {
             let sel = f.first().unwrap();
-            if !fonts.contains(&format!("Noto {sel}")) {
+            if !fonts.contains(sel) {
                 cprint!("\x1b[96mneed\x1b[m {sel} for u+{c:04x}");
-                fonts.push(format!("Noto {sel}"));
+                fonts.push(sel.clone());
             }
         }
-        cprint!("determined necessary fonts");
-        FontStack { names: fonts, map: font_support.clone() }
+        fonts
+    }
+
+    /// picks the fonts with the fewest total bytes that cover the codepoints (greedily, by bytes
+    /// per newly covered codepoint), stopping at the budget. also returns what didn't fit
+    fn select_by_size(
+        &self,
+        codepoints: &[u32],
+        config: &NotoizeConfig,
+        sizes: &HashMap<FontFamily, usize>,
+    ) -> (Vec<FontFamily>, Vec<u32>) {
+        let max = sizes.values().max().copied().unwrap_or_default();
+        let size = |f: &FontFamily| sizes.get(f).copied().unwrap_or(max);
+        let candidates = codepoints
+            .iter()
+            .map(|c| (*c, self.candidates(*c, config)))
+            .filter(|(_, f)| !f.is_empty())
+            .collect::<HashMap<_, _>>();
+        // the codepoints left that each font covers, kept up to date as fonts get picked
+        let mut covers = HashMap::<FontFamily, HashSet<u32>>::new();
+        for (c, fonts) in &candidates {
+            for f in fonts {
+                covers.entry(f.clone()).or_default().insert(*c);
+            }
+        }
+        let mut left = candidates.keys().copied().collect::<HashSet<_>>();
+        let mut fonts = vec![];
+        let mut total = 0;
+        while !left.is_empty() {
+            let Some(sel) = covers
+                .iter()
+                .filter(|(f, covered)| {
+                    !covered.is_empty() && config.budget.is_none_or(|b| total + size(f) <= b)
+                })
+                .min_by_key(|(f, covered)| {
+                    // bytes per codepoint, scaled up so the division keeps some precision
+                    let per = ((size(f) as u128) << 32) / covered.len() as u128;
+                    (per, config.preference(f), *f)
+                })
+                .map(|(f, _)| f.clone())
+            else {
+                break;
+            };
+            let covered = covers.remove(&sel).unwrap_or_default();
+            cprint!("\x1b[96mneed\x1b[m {sel} for {} codepoints", covered.len());
+            total += size(&sel);
+            for c in covered {
+                left.remove(&c);
+                for f in &candidates[&c] {
+                    if let Some(cps) = covers.get_mut(f) {
+                        cps.remove(&c);
+                    }
+                }
+            }
+            fonts.push(sel);
+        }
+        (fonts, left.into_iter().sorted().collect())
+    }
+
+    /// whether the font has `vert` or `vrt2`, fetching it the first time
+    /// # Errors
+    /// if the font can't be fetched
+    pub fn vertical_features(&mut self, f: &FontFamily) -> Result<bool, String> {
+        if let Some(has) = self.vertical_features.get(f) {
+            return Ok(*has);
+        }
+        cprint!("\x1b[92mfetching\x1b[m {f}");
+        let bytes = self.manifest.resolve(f).fetch()?;
+        let has =
+            Font { filename: String::new(), fontname: f.clone(), bytes }.has_vertical_features();
+        self.vertical_features.insert(f.clone(), has);
+        Ok(has)
+    }
+
+    /// checks the CJK fonts for `vert`/`vrt2`, returning the ones without them and the ones that
+    /// couldn't be fetched to check. each region has one family per class, so there's nothing
+    /// to swap them for
+    fn check_vertical_features(
+        &mut self,
+        fonts: &[FontFamily],
+    ) -> (Vec<FontFamily>, Vec<FontFamily>) {
+        let (mut missing, mut unverified) = (vec![], vec![]);
+        for f in fonts.iter().filter(|f| f.script() == Script::Cjk) {
+            match self.vertical_features(f) {
+                Ok(true) => {}
+                Ok(false) => missing.push(f.clone()),
+                Err(_) => unverified.push(f.clone()),
+            }
+        }
+        (missing, unverified)
+    }
+
+    /// makes sure every script in the stack has a font covering its shaping support characters,
+    /// returning those characters. ones whose font doesn't fit in the budget go in `over_budget`
+    fn add_shaping_support(
+        &mut self,
+        fonts: &mut Vec<FontFamily>,
+        config: &NotoizeConfig,
+        over_budget: &mut Vec<u32>,
+    ) -> Vec<u32> {
+        let needed = fonts
+            .iter()
+            .map(FontFamily::script)
+            .unique()
+            .flat_map(|s| shaping_support(&s).iter().map(move |c| (s.clone(), *c)))
+            .collect_vec();
+        let codepoints = needed.iter().map(|(_, c)| *c).sorted().dedup().collect_vec();
+        self.load_blocks(&codepoints);
+        for (s, c) in needed {
+            let candidates = self.candidates(c, config);
+            if candidates.iter().any(|f| fonts.contains(f) && f.script() == s) {
+                continue;
+            }
+            if let Some(sel) = candidates.into_iter().find(|f| f.script() == s) {
+                if !config.fits(&fonts.iter().chain([&sel]).cloned().collect_vec()) {
+                    over_budget.push(c);
+                    continue;
+                }
+                cprint!("\x1b[96mneed\x1b[m {sel} for u+{c:04x} (shaping)");
+                fonts.push(sel);
+            }
+        }
+        codepoints
+    }
+}
+
+/// the result of [`NotoizeClient::single_font`]
+#[derive(Debug, Clone)]
+pub enum SingleFont {
+    /// every font that covers the whole text, most preferred first
+    Full(Vec<FontFamily>),
+    /// no font covers the whole text; this one covers the most of it
+    Partial { font: FontFamily, missing: Vec<u32> },
+    /// no font covers any of the text
+    Unsupported,
+}
+
+/// options for [`NotoizeClient::notoize_with`]
+#[derive(Debug, Clone)]
+pub struct NotoizeConfig {
+    /// for every script in the stack, also require coverage of the characters its shaping
+    /// engine inserts on its own (dotted circle, ZWJ/ZWNJ, joiners...), from a font of that script
+    pub shaping_support: bool,
+    /// control characters (Cc)
+    pub controls: CharPolicy,
+    /// format characters (Cf) that aren't drawn, like bidi controls, ZWJ or U+FEFF
+    pub format: CharPolicy,
+    /// space separators (Zs)
+    pub spaces: CharPolicy,
+    /// line and paragraph separators (Zl, Zp)
+    pub separators: CharPolicy,
+    /// which normalization form(s) of the text to cover
+    pub normalization: Normalization,
+    /// also cover the text in uppercase, like `text-transform: uppercase` (ß → SS)
+    pub uppercase: bool,
+    /// also cover the text in lowercase
+    pub lowercase: bool,
+    /// also cover the titlecase form of every character, like `text-transform: capitalize`
+    pub titlecase: bool,
+    /// set the text vertically: prefer vertical families, check that CJK fonts have `vert` or
+    /// `vrt2`, and cover the vertical presentation forms of the text's punctuation
+    pub vertical: bool,
+    /// file size of each font, to pick the stack with the fewest total bytes instead of the
+    /// preferred fonts. fonts that aren't listed count as the biggest one that is. see
+    /// [`Manifest::sizes`] and [`file_sizes`]
+    pub sizes: Option<HashMap<FontFamily, usize>>,
+    /// with `sizes`, the most bytes the stack can take up
+    pub budget: Option<usize>,
+    /// how often each codepoint occurs, for telling critical fonts from optional ones. counted
+    /// from the text if not given
+    pub frequencies: Option<HashMap<u32, usize>>,
+    /// the share of characters a font has to render to be critical
+    pub critical_share: f64,
+    /// ...or the number of characters
+    pub critical_count: usize,
+    /// characters that only fonts for historic and liturgical scripts
+    /// ([`ScriptStatus::Excluded`]) support. anything but `Require` also keeps those fonts from
+    /// being picked for other characters
+    pub historic: CharPolicy,
+}
+
+impl Default for NotoizeConfig {
+    fn default() -> Self {
+        Self {
+            shaping_support: false,
+            controls: CharPolicy::Ignore,
+            format: CharPolicy::Ignore,
+            spaces: CharPolicy::Require,
+            separators: CharPolicy::Ignore,
+            normalization: Normalization::None,
+            uppercase: false,
+            lowercase: false,
+            titlecase: false,
+            vertical: false,
+            sizes: None,
+            budget: None,
+            frequencies: None,
+            critical_share: 0.01,
+            critical_count: 1000,
+            historic: CharPolicy::Require,
+        }
+    }
+}
+
+impl NotoizeConfig {
+    /// what to do with a codepoint from the text
+    pub fn char_policy(&self, c: u32) -> CharPolicy {
+        let Some(ch) = char::from_u32(c) else { return CharPolicy::Require };
+        match get_general_category(ch) {
+            GeneralCategory::Control => self.controls,
+            GeneralCategory::Format if !is_visible_format(c) => self.format,
+            GeneralCategory::SpaceSeparator => self.spaces,
+            GeneralCategory::LineSeparator | GeneralCategory::ParagraphSeparator => self.separators,
+            _ => CharPolicy::Require,
+        }
+    }
+
+    /// whether the fonts fit in the [`budget`](Self::budget) together, counting fonts without a
+    /// size like [`sizes`](Self::sizes) says
+    fn fits(&self, fonts: &[FontFamily]) -> bool {
+        let (Some(sizes), Some(budget)) = (&self.sizes, self.budget) else { return true };
+        let max = sizes.values().max().copied().unwrap_or_default();
+        fonts.iter().map(|f| sizes.get(f).copied().unwrap_or(max)).sum::<usize>() <= budget
+    }
+
+    /// sort key for picking between fonts, lowest first
+    fn preference(&self, font: &FontFamily) -> (bool, bool, FontFamily) {
+        (
+            self.vertical && !font.qualifiers().contains(&Qualifier::Vertical),
+            !font.0.contains("Sans"),
+            font.clone(),
+        )
+    }
+
+    /// the codepoints of the text that need coverage
+    fn codepoints(&self, text: &str) -> Codepoints {
+        let text = &self.with_case_mappings(text);
+        let text = &if self.vertical { text.clone() + &vertical_forms(text) } else { text.clone() };
+        let source = text.chars().map(|c| c as u32).sorted().dedup().collect_vec();
+        let mut codepoints =
+            self.normalization.apply(text).chars().map(|c| c as u32).sorted().dedup().collect_vec();
+        let normalized =
+            codepoints.iter().filter(|c| source.binary_search(c).is_err()).copied().collect_vec();
+        let mut invisible = vec![];
+        codepoints.retain(|c| match self.char_policy(*c) {
+            CharPolicy::Require => true,
+            CharPolicy::Report => {
+                invisible.push(*c);
+                false
+            }
+            CharPolicy::Ignore => false,
+        });
+        Codepoints { codepoints, invisible, normalized }
+    }
+
+    /// the text followed by whichever case mappings of it are enabled
+    pub fn with_case_mappings(&self, text: &str) -> String {
+        let mut res = text.to_string();
+        if self.uppercase {
+            res += &text.to_uppercase();
+        }
+        if self.lowercase {
+            res += &text.to_lowercase();
+        }
+        if self.titlecase {
+            // any character can start a word, so titlecase all of them
+            res.extend(text.chars().flat_map(|c| match to_titlecase(c) {
+                [0, 0, 0] => vec![c],
+                t => t.into_iter().filter(|t| *t != 0).filter_map(char::from_u32).collect(),
+            }));
+        }
+        res
+    }
+}
+
+struct Codepoints {
+    codepoints: Vec<u32>,
+    invisible: Vec<u32>,
+    normalized: Vec<u32>,
+}
+
+/// how to treat a class of characters that renderers usually don't draw with a font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharPolicy {
+    /// leave them out entirely
+    Ignore,
+    /// require coverage like any other character
+    Require,
+    /// leave them out of the coverage check and list them on the [`FontStack`]
+    Report,
+}
+
+/// which form of the text to compute coverage for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// the text as is
+    None,
+    Nfc,
+    Nfd,
+    Nfkc,
+    /// the text plus its NFC, NFD, NFKC and NFKD forms, for text whose origin is unknown
+    All,
+}
+
+impl Normalization {
+    /// the text to compute coverage for
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Self::None => text.to_string(),
+            Self::Nfc => text.nfc().collect(),
+            Self::Nfd => text.nfd().collect(),
+            Self::Nfkc => text.nfkc().collect(),
+            Self::All => [
+                text.to_string(),
+                text.nfc().collect(),
+                text.nfd().collect(),
+                text.nfkc().collect(),
+                text.nfkd().collect(),
+            ]
+            .concat(),
+        }
     }
 }
 
+/// the file size of each font, for [`NotoizeConfig::sizes`]
+pub fn file_sizes(fonts: &[Font]) -> HashMap<FontFamily, usize> {
+    fonts.iter().map(|f| (f.fontname.clone(), f.bytes.len())).collect()
+}
+
+/// the vertical presentation forms (U+FE10..FE1F, U+FE30..FE4F) that vertical layout can
+/// substitute for characters in the text
+pub fn vertical_forms(text: &str) -> String {
+    let forms = text.chars().map(|c| c.nfkc().collect::<String>()).collect::<HashSet<_>>();
+    (0xfe10..=0xfe1f)
+        .chain(0xfe30..=0xfe4f)
+        .filter_map(char::from_u32)
+        .filter(|v| forms.contains(&v.nfkc().collect::<String>()))
+        .collect()
+}
+
+/// a CSS `unicode-range` value for some sorted codepoints
+fn unicode_range(codepoints: &[u32]) -> String {
+    ranges(codepoints)
+        .into_iter()
+        .map(
+            |(start, end)| {
+                if start == end {
+                    format!("U+{start:x}")
+                } else {
+                    format!("U+{start:x}-{end:x}")
+                }
+            },
+        )
+        .join(", ")
+}
+
+/// the name of a font's file among `files`, or upstream's if it isn't one of them
+fn filename_in(files: &[Font], f: &FontFamily) -> String {
+    files.iter().find(|x| x.fontname == *f).map_or_else(|| f.filename(), |x| x.filename.clone())
+}
+
+/// the name of the LaTeX command for a font, which can only have letters
+fn latex_command(f: &FontFamily) -> String {
+    const DIGITS: [&str; 10] =
+        ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+    let name = f.css_name();
+    let name = name.chars().filter(|c| *c != ' ').map(|c| match c.to_digit(10) {
+        Some(d) => DIGITS[d as usize].to_string(),
+        None => c.to_string(),
+    });
+    format!("\\{}", name.collect::<String>().to_lowercase())
+}
+
+/// the runs of consecutive codepoints in some sorted codepoints, inclusive
+fn ranges(codepoints: &[u32]) -> Vec<(u32, u32)> {
+    let mut ranges = Vec::<(u32, u32)>::new();
+    for c in codepoints {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *c => *end = *c,
+            _ => ranges.push((*c, *c)),
+        }
+    }
+    ranges
+}
+
+/// format characters that do get drawn (or shaped) with glyphs from the font
+fn is_visible_format(c: u32) -> bool {
+    matches!(c,
+        // soft hyphen
+        0xad
+        // prepended concatenation marks
+        | 0x600..=0x605 | 0x6dd | 0x70f | 0x890..=0x891 | 0x8e2 | 0x110bd | 0x110cd
+        // egyptian hieroglyph format controls
+        | 0x13430..=0x1345f
+        // duployan shorthand format controls
+        | 0x1bca0..=0x1bca3
+        // emoji tag sequences
+        | 0xe0020..=0xe007f
+    )
+}
+
 macro_rules! generate_script {
-    ($($($font:literal)|* => $script:literal),* $(,)?) => {
-        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-        pub struct Script(String);
+    ($(
+        $($font:literal)|* =>
+            $variant:ident($script:literal, $iso:literal, $dir:ident, $status:ident)
+    ),* $(,)?) => {
+        /// a group of noto families for the same script
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        pub enum Script {
+            $($variant,)*
+            /// a family that isn't in the table yet, by its name
+            Unknown(String),
+        }
+        impl Script {
+            /// every script in the table
+            pub const ALL: &[Self] = &[$(Self::$variant),*];
+            /// the name used in reports, `""` for latin, greek and cyrillic, the family's name for
+            /// unknown ones
+            pub fn name(&self) -> &str {
+                match self {
+                    $(Self::$variant => $script,)*
+                    Self::Unknown(font) => font,
+                }
+            }
+            /// the ISO 15924 code. Zyyy, Zinh, Zmth, Zsym and Zsye for the fonts that aren't
+            /// for one script, Zzzz for unknown ones
+            pub fn iso15924(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $iso,)*
+                    Self::Unknown(_) => "Zzzz",
+                }
+            }
+            pub fn direction(&self) -> Direction {
+                match self {
+                    $(Self::$variant => Direction::$dir,)*
+                    Self::Unknown(_) => Direction::Ltr,
+                }
+            }
+            /// unknown families are assumed recommended so they still get selected
+            pub fn status(&self) -> ScriptStatus {
+                match self {
+                    $(Self::$variant => ScriptStatus::$status,)*
+                    Self::Unknown(_) => ScriptStatus::Recommended,
+                }
+            }
+            pub fn families(&self) -> Vec<FontFamily> {
+                match self {
+                    $(Self::$variant => vec![$(FontFamily::new($font)),*],)*
+                    Self::Unknown(font) => vec![FontFamily::new(font)],
+                }
+            }
+        }
+        /// the script of a family, [`Script::Unknown`] if it isn't in the table
         pub fn script(font: &str) -> Script {
             match font {
                 $(
-                    $($font)|* => Script($script.to_string()),
+                    $($font)|* => Script::$variant,
                 )*
-                _ => panic!(
-                        "unknown font name `\x1b[91m{font}\x1b[m` - please file an issue on the github repo or i'll catch \
-                        it in up to three months"
-                     )
+                _ => Script::Unknown(font.to_string()),
             }
         }
-        pub fn all_variants() -> Vec<String> {
+        pub fn all_variants() -> Vec<FontFamily> {
             let a = [$($($font),*),*];
-            a.into_iter().map(|v| v.to_string()).collect_vec()
+            a.into_iter().map(FontFamily::new).collect_vec()
         }
     }
 }
 
 generate_script! {
     // check via / ((?!Sans|Serif)[a-zA-Z]+)([ ,]|$).*\n.* \1([ ,]|$)/
-    "Sans" | "Serif" | "Sans Mono" => "",
-    "Sans Adlam" | "Sans Adlam Unjoined" => "Adlam",
-    "Serif Ahom" => "Ahom",
-    "Sans AnatoHiero" => "Anatolian Hieroglyphs",
-    "Sans Arabic" | "Kufi Arabic" | "Naskh Arabic" | "Nastaliq Urdu" => "Arabic",
-    "Sans Armenian" | "Serif Armenian" => "Armenian",
-    "Sans Avestan" => "Avestan",
-    "Sans Balinese" | "Serif Balinese" => "Balinese",
-    "Sans Bamum" => "Bamum",
-    "Sans Bassa Vah" => "Bassa Vah",
-    "Sans Batak" => "Batak",
-    "Sans Bengali" | "Serif Bengali" => "Bengali",
-    "Sans Bhaiksuki" => "Bhaiksuki",
-    "Sans Brahmi" => "Brahmi",
-    "Sans Buginese" => "Buginese",
-    "Sans Buhid" => "Buhid",
-    "Sans Canadian Aboriginal" => "Canadian Aboriginal",
-    "Sans Carian" => "Carian",
-    "Sans Caucasian Albanian" => "Caucasian Albanian",
-    "Sans Chakma" => "Chakma",
-    "Sans Cham" => "Cham",
-    "Sans Cherokee" => "Cherokee",
-    "Sans Chorasmian" => "Chorasmian",
-    "Sans CJK HK" | "Sans CJK JP" | "Sans CJK KR" | "Sans CJK SC" | "Sans CJK TC" => "CJK",
-    "Sans Coptic" => "Coptic",
-    "Sans Cuneiform" => "Cuneiform",
-    "Sans Cypriot" => "Cypriot",
-    "Sans Cypro Minoan" => "Cypro Minoan",
-    "Sans Deseret" => "Deseret",
-    "Sans Devanagari" | "Serif Devanagari" => "Devanagari",
-    "Serif Dives Akuru" => "Dives Akuru",
-    "Serif Dogra" => "Dogra",
-    "Sans Duployan" => "Duployan",
-    "Sans EgyptHiero" => "Egyptian Hieroglyphs",
-    "Sans Elbasan" => "Elbasan",
-    "Sans Elymaic" => "Elymaic",
-    "Color Emoji" => "Emoji",
-    "Sans Ethiopic" | "Serif Ethiopic" => "Ethiopic",
-    "Sans Georgian" | "Serif Georgian" => "Georgian",
-    "Sans Glagolitic" => "Glagolitic",
-    "Sans Gothic" => "Gothic",
-    "Sans Grantha" | "Serif Grantha" => "Grantha",
-    "Sans Gujarati" | "Serif Gujarati" => "Gujarati",
-    "Sans Gunjala Gondi" => "Gunjala Gondi",
-    "Sans Gurmukhi" | "Serif Gurmukhi" => "Gurmukhi",
-    "Sans Hanifi Rohingya" => "Hanifi Rohingya",
-    "Sans Hanunoo" => "Hanunoo",
-    "Sans Hatran" => "Hatran",
-    "Sans Hebrew" | "Serif Hebrew" | "Rashi Hebrew" => "Hebrew",
-    "Sans ImpAramaic" => "Imperial Aramaic",
-    "Sans Indic Siyaq Numbers" => "Indic Siyaq Numbers",
-    "Sans InsPahlavi" => "Inscriptional Pahlavi",
-    "Sans Inscriptional Parthian" => "Inscriptional Parthian",
-    "Sans Javanese" => "Javanese",
-    "Sans Kaithi" => "Kaithi",
-    "Sans Kannada" | "Serif Kannada" => "Kannada",
-    "Sans Kawi" => "Kawi",
-    "Serif Khitan Small Script" | "Fangsong KSS Rotated" | "Fangsong KSS Vertical" => "Khitan",
-    "Sans Kayah Li" => "Kayah Li",
-    "Sans Kharoshthi" => "Kharoshthi",
-    "Sans Khmer" | "Serif Khmer" => "Khmer",
-    "Sans Khojki" | "Serif Khojki" => "Khojki",
-    "Sans Khudawadi" => "Khudawadi",
-    "Sans Lao" | "Sans Lao Looped" | "Serif Lao" => "Lao",
-    "Sans Lepcha" => "Lepcha",
-    "Sans Limbu" => "Limbu",
-    "Sans Linear A" => "Linear A",
-    "Sans Linear B" => "Linear B",
-    "Sans Lisu" => "Lisu",
-    "Sans Lycian" => "Lycian",
-    "Sans Lydian" => "Lydian",
-    "Sans Mahajani" => "Mahajani",
-    "Sans Malayalam" | "Serif Malayalam" => "Malayalam",
-    "Sans Mandaic" => "Mandaic",
-    "Serif Makasar" => "Makasar",
-    "Sans Manichaean" => "Manichaean",
-    "Sans Masaram Gondi" => "Masaram Gondi",
-    "Sans Marchen" => "Marchen",
-    "Sans Math" => "Math",
-    "Sans Mayan Numerals" => "Mayan Numerals",
-    "Sans Medefaidrin" => "Medefaidrin",
-    "Sans Meetei Mayek" => "Meetei Mayek",
-    "Sans Mende Kikakui" => "Mende Kikakui",
-    "Sans Meroitic" => "Meroitic",
-    "Sans Miao" => "Miao",
-    "Sans Modi" => "Modi",
-    "Sans Mongolian" => "Mongolian",
-    "Sans Mro" => "Mro",
-    "Sans Multani" => "Multani",
-    "Music" => "Music",
-    "Sans Myanmar" | "Serif Myanmar" => "Myanmar",
-    "Sans Nabataean" => "Nabataean",
-    "Sans Nag Mundari" => "Nag Mundari",
-    "Sans Nandinagari" => "Nandinagari",
-    "Sans New Tai Lue" => "New Tai Lue",
-    "Sans Newa" => "Newa",
-    "Sans NKo" | "Sans NKo Unjoined" => "NKo",
-    "Sans Nushu" | "Traditional Nushu" => "Nushu",
-    "Serif NP Hmong" => "Nyiakeng Puachue Hmong",
-    "Sans Ogham" => "Ogham",
-    "Sans Ol Chiki" => "Ol Chiki",
-    "Sans OldHung" => "Old Hungarian",
-    "Sans Old Italic" => "Old Italic",
-    "Sans OldNorArab" => "Old North Arabian",
-    "Sans Old Permic" => "Old Permic",
-    "Sans OldPersian" => "Old Persian",
-    "Sans OldSogdian" => "Old Sogdian",
-    "Sans OldSouArab" => "Old South Arabian",
-    "Sans Old Turkic" => "Old Turkic",
-    "Serif Old Uyghur" => "Old Uyghur",
-    "Sans Oriya" | "Serif Oriya" => "Oriya",
-    "Sans Osage" => "Osage",
-    "Sans Osmanya" => "Osmanya",
-    "Serif Ottoman Siyaq" => "Ottoman Siyaq",
-    "Sans Pahawh Hmong" => "Pahawh Hmong",
-    "Sans Palmyrene" => "Palmyrene",
-    "Sans PauCinHau" => "Pau Cin Hau",
-    "Sans PhagsPa" => "Phags-Pa",
-    "Sans Phoenician" => "Phoenician",
-    "Sans PsaPahlavi" => "Psalter Pahlavi",
-    "Sans Rejang" => "Rejang",
-    "Sans Runic" => "Runic",
-    "Sans Samaritan" => "Samaritan",
-    "Sans Saurashtra" => "Saurashtra",
-    "Sans Sharada" => "Sharada",
-    "Sans Shavian" => "Shavian",
-    "Sans Siddham" => "Siddham",
-    "Sans SignWriting" => "SignWriting",
-    "Sans Sinhala" | "Serif Sinhala" => "Sinhala",
-    "Sans Sogdian" => "Sogdian",
-    "Sans Sora Sompeng" => "Sora Sompeng",
-    "Sans Soyombo" => "Soyombo",
-    "Sans Sundanese" => "Sundanese",
-    "Sans Sunuwar" => "Sunuwar",
-    "Sans Syloti Nagri" => "Syloti Nagri",
-    "Sans Symbols" => "Symbols",
-    "Sans Symbols 2" => "Symbols 2", // there are only ~15 characters that both support
-    "Sans Syriac" | "Sans Syriac Eastern" | "Sans Syriac Western" => "Syriac",
-    "Sans Tamil" | "Serif Tamil" => "Tamil",
-    "Sans Tamil Supplement" => "Tamil Supplement",
-    "Sans Tagalog" => "Tagalog",
-    "Sans Tagbanwa" => "Tagbanwa",
-    "Sans Tai Le" => "Tai Le",
-    "Sans Tai Tham" => "Tai Tham",
-    "Sans Tai Viet" => "Tai Viet",
-    "Sans Takri" => "Takri",
-    "Sans Tangsa" => "Tangsa",
-    "Serif Tangut" => "Tangut",
-    "Sans Telugu" | "Serif Telugu" => "Telugu",
-    "Sans Thaana" => "Thaana",
-    "Sans Thai" | "Sans Thai Looped" | "Serif Thai" => "Thai",
-    "Serif Tibetan" => "Tibetan",
+    "Sans" | "Serif" | "Sans Mono" => LatinGreekCyrillic("", "Latn", Ltr, Recommended),
+    "Sans Adlam" | "Sans Adlam Unjoined" => Adlam("Adlam", "Adlm", Rtl, LimitedUse),
+    "Serif Ahom" => Ahom("Ahom", "Ahom", Ltr, Excluded),
+    "Sans AnatoHiero" => AnatolianHieroglyphs("Anatolian Hieroglyphs", "Hluw", Ltr, Excluded),
+    "Sans Arabic"
+    | "Kufi Arabic"
+    | "Naskh Arabic"
+    | "Nastaliq Urdu" => Arabic("Arabic", "Arab", Rtl, Recommended),
+    "Sans Armenian" | "Serif Armenian" => Armenian("Armenian", "Armn", Ltr, Recommended),
+    "Sans Avestan" => Avestan("Avestan", "Avst", Rtl, Excluded),
+    "Sans Balinese" | "Serif Balinese" => Balinese("Balinese", "Bali", Ltr, LimitedUse),
+    "Sans Bamum" => Bamum("Bamum", "Bamu", Ltr, LimitedUse),
+    "Sans Bassa Vah" => BassaVah("Bassa Vah", "Bass", Ltr, Excluded),
+    "Sans Batak" => Batak("Batak", "Batk", Ltr, LimitedUse),
+    "Sans Bengali" | "Serif Bengali" => Bengali("Bengali", "Beng", Ltr, Recommended),
+    "Sans Bhaiksuki" => Bhaiksuki("Bhaiksuki", "Bhks", Ltr, Excluded),
+    "Sans Brahmi" => Brahmi("Brahmi", "Brah", Ltr, Excluded),
+    "Sans Buginese" => Buginese("Buginese", "Bugi", Ltr, Excluded),
+    "Sans Buhid" => Buhid("Buhid", "Buhd", Ltr, Excluded),
+    "Sans Canadian Aboriginal" =>
+        CanadianAboriginal("Canadian Aboriginal", "Cans", Ltr, LimitedUse),
+    "Sans Carian" => Carian("Carian", "Cari", Ltr, Excluded),
+    "Sans Caucasian Albanian" => CaucasianAlbanian("Caucasian Albanian", "Aghb", Ltr, Excluded),
+    "Sans Chakma" => Chakma("Chakma", "Cakm", Ltr, LimitedUse),
+    "Sans Cham" => Cham("Cham", "Cham", Ltr, LimitedUse),
+    "Sans Cherokee" => Cherokee("Cherokee", "Cher", Ltr, LimitedUse),
+    "Sans Chorasmian" => Chorasmian("Chorasmian", "Chrs", Rtl, Excluded),
+    "Sans CJK HK"
+    | "Sans CJK JP"
+    | "Sans CJK KR"
+    | "Sans CJK SC"
+    | "Sans CJK TC" => Cjk("CJK", "Hani", Ltr, Recommended),
+    "Sans Coptic" => Coptic("Coptic", "Copt", Ltr, Excluded),
+    "Sans Cuneiform" => Cuneiform("Cuneiform", "Xsux", Ltr, Excluded),
+    "Sans Cypriot" => Cypriot("Cypriot", "Cprt", Rtl, Excluded),
+    "Sans Cypro Minoan" => CyproMinoan("Cypro Minoan", "Cpmn", Ltr, Excluded),
+    "Sans Deseret" => Deseret("Deseret", "Dsrt", Ltr, Excluded),
+    "Sans Devanagari" | "Serif Devanagari" => Devanagari("Devanagari", "Deva", Ltr, Recommended),
+    "Serif Dives Akuru" => DivesAkuru("Dives Akuru", "Diak", Ltr, Excluded),
+    "Serif Dogra" => Dogra("Dogra", "Dogr", Ltr, Excluded),
+    "Sans Duployan" => Duployan("Duployan", "Dupl", Ltr, Excluded),
+    "Sans EgyptHiero" => EgyptianHieroglyphs("Egyptian Hieroglyphs", "Egyp", Ltr, Excluded),
+    "Sans Elbasan" => Elbasan("Elbasan", "Elba", Ltr, Excluded),
+    "Sans Elymaic" => Elymaic("Elymaic", "Elym", Rtl, Excluded),
+    "Color Emoji" => Emoji("Emoji", "Zsye", Ltr, Recommended),
+    "Sans Ethiopic" | "Serif Ethiopic" => Ethiopic("Ethiopic", "Ethi", Ltr, Recommended),
+    "Sans Georgian" | "Serif Georgian" => Georgian("Georgian", "Geor", Ltr, Recommended),
+    "Sans Glagolitic" => Glagolitic("Glagolitic", "Glag", Ltr, Excluded),
+    "Sans Gothic" => Gothic("Gothic", "Goth", Ltr, Excluded),
+    "Sans Grantha" | "Serif Grantha" => Grantha("Grantha", "Gran", Ltr, Excluded),
+    "Sans Gujarati" | "Serif Gujarati" => Gujarati("Gujarati", "Gujr", Ltr, Recommended),
+    "Sans Gunjala Gondi" => GunjalaGondi("Gunjala Gondi", "Gong", Ltr, LimitedUse),
+    "Sans Gurmukhi" | "Serif Gurmukhi" => Gurmukhi("Gurmukhi", "Guru", Ltr, Recommended),
+    "Sans Hanifi Rohingya" => HanifiRohingya("Hanifi Rohingya", "Rohg", Rtl, LimitedUse),
+    "Sans Hanunoo" => Hanunoo("Hanunoo", "Hano", Ltr, Excluded),
+    "Sans Hatran" => Hatran("Hatran", "Hatr", Rtl, Excluded),
+    "Sans Hebrew" | "Serif Hebrew" | "Rashi Hebrew" => Hebrew("Hebrew", "Hebr", Rtl, Recommended),
+    "Sans ImpAramaic" => ImperialAramaic("Imperial Aramaic", "Armi", Rtl, Excluded),
+    "Sans Indic Siyaq Numbers" => IndicSiyaqNumbers("Indic Siyaq Numbers", "Zyyy", Rtl, Excluded),
+    "Sans InsPahlavi" => InscriptionalPahlavi("Inscriptional Pahlavi", "Phli", Rtl, Excluded),
+    "Sans Inscriptional Parthian" =>
+        InscriptionalParthian("Inscriptional Parthian", "Prti", Rtl, Excluded),
+    "Sans Javanese" => Javanese("Javanese", "Java", Ltr, LimitedUse),
+    "Sans Kaithi" => Kaithi("Kaithi", "Kthi", Ltr, Excluded),
+    "Sans Kannada" | "Serif Kannada" => Kannada("Kannada", "Knda", Ltr, Recommended),
+    "Sans Kawi" => Kawi("Kawi", "Kawi", Ltr, Excluded),
+    "Serif Khitan Small Script"
+    | "Fangsong KSS Rotated"
+    | "Fangsong KSS Vertical" => Khitan("Khitan", "Kits", Ltr, Excluded),
+    "Sans Kayah Li" => KayahLi("Kayah Li", "Kali", Ltr, LimitedUse),
+    "Sans Kharoshthi" => Kharoshthi("Kharoshthi", "Khar", Rtl, Excluded),
+    "Sans Khmer" | "Serif Khmer" => Khmer("Khmer", "Khmr", Ltr, Recommended),
+    "Sans Khojki" | "Serif Khojki" => Khojki("Khojki", "Khoj", Ltr, Excluded),
+    "Sans Khudawadi" => Khudawadi("Khudawadi", "Sind", Ltr, Excluded),
+    "Sans Lao" | "Sans Lao Looped" | "Serif Lao" => Lao("Lao", "Laoo", Ltr, Recommended),
+    "Sans Lepcha" => Lepcha("Lepcha", "Lepc", Ltr, LimitedUse),
+    "Sans Limbu" => Limbu("Limbu", "Limb", Ltr, LimitedUse),
+    "Sans Linear A" => LinearA("Linear A", "Lina", Ltr, Excluded),
+    "Sans Linear B" => LinearB("Linear B", "Linb", Ltr, Excluded),
+    "Sans Lisu" => Lisu("Lisu", "Lisu", Ltr, LimitedUse),
+    "Sans Lycian" => Lycian("Lycian", "Lyci", Ltr, Excluded),
+    "Sans Lydian" => Lydian("Lydian", "Lydi", Rtl, Excluded),
+    "Sans Mahajani" => Mahajani("Mahajani", "Mahj", Ltr, Excluded),
+    "Sans Malayalam" | "Serif Malayalam" => Malayalam("Malayalam", "Mlym", Ltr, Recommended),
+    "Sans Mandaic" => Mandaic("Mandaic", "Mand", Rtl, LimitedUse),
+    "Serif Makasar" => Makasar("Makasar", "Maka", Ltr, Excluded),
+    "Sans Manichaean" => Manichaean("Manichaean", "Mani", Rtl, Excluded),
+    "Sans Masaram Gondi" => MasaramGondi("Masaram Gondi", "Gonm", Ltr, LimitedUse),
+    "Sans Marchen" => Marchen("Marchen", "Marc", Ltr, Excluded),
+    "Sans Math" => Math("Math", "Zmth", Ltr, Recommended),
+    "Sans Mayan Numerals" => MayanNumerals("Mayan Numerals", "Zyyy", Ltr, Excluded),
+    "Sans Medefaidrin" => Medefaidrin("Medefaidrin", "Medf", Ltr, Excluded),
+    "Sans Meetei Mayek" => MeeteiMayek("Meetei Mayek", "Mtei", Ltr, LimitedUse),
+    "Sans Mende Kikakui" => MendeKikakui("Mende Kikakui", "Mend", Rtl, Excluded),
+    "Sans Meroitic" => Meroitic("Meroitic", "Merc", Rtl, Excluded),
+    "Sans Miao" => Miao("Miao", "Plrd", Ltr, LimitedUse),
+    "Sans Modi" => Modi("Modi", "Modi", Ltr, Excluded),
+    "Sans Mongolian" => Mongolian("Mongolian", "Mong", Ttb, LimitedUse),
+    "Sans Mro" => Mro("Mro", "Mroo", Ltr, Excluded),
+    "Sans Multani" => Multani("Multani", "Mult", Ltr, Excluded),
+    "Music" => Music("Music", "Zsym", Ltr, Recommended),
+    "Sans Myanmar" | "Serif Myanmar" => Myanmar("Myanmar", "Mymr", Ltr, Recommended),
+    "Sans Nabataean" => Nabataean("Nabataean", "Nbat", Rtl, Excluded),
+    "Sans Nag Mundari" => NagMundari("Nag Mundari", "Nagm", Ltr, LimitedUse),
+    "Sans Nandinagari" => Nandinagari("Nandinagari", "Nand", Ltr, Excluded),
+    "Sans New Tai Lue" => NewTaiLue("New Tai Lue", "Talu", Ltr, LimitedUse),
+    "Sans Newa" => Newa("Newa", "Newa", Ltr, LimitedUse),
+    "Sans NKo" | "Sans NKo Unjoined" => NKo("NKo", "Nkoo", Rtl, LimitedUse),
+    "Sans Nushu" | "Traditional Nushu" => Nushu("Nushu", "Nshu", Ltr, Excluded),
+    "Serif NP Hmong" => NyiakengPuachueHmong("Nyiakeng Puachue Hmong", "Hmnp", Ltr, LimitedUse),
+    "Sans Ogham" => Ogham("Ogham", "Ogam", Ltr, Excluded),
+    "Sans Ol Chiki" => OlChiki("Ol Chiki", "Olck", Ltr, LimitedUse),
+    "Sans OldHung" => OldHungarian("Old Hungarian", "Hung", Rtl, Excluded),
+    "Sans Old Italic" => OldItalic("Old Italic", "Ital", Ltr, Excluded),
+    "Sans OldNorArab" => OldNorthArabian("Old North Arabian", "Narb", Rtl, Excluded),
+    "Sans Old Permic" => OldPermic("Old Permic", "Perm", Ltr, Excluded),
+    "Sans OldPersian" => OldPersian("Old Persian", "Xpeo", Ltr, Excluded),
+    "Sans OldSogdian" => OldSogdian("Old Sogdian", "Sogo", Rtl, Excluded),
+    "Sans OldSouArab" => OldSouthArabian("Old South Arabian", "Sarb", Rtl, Excluded),
+    "Sans Old Turkic" => OldTurkic("Old Turkic", "Orkh", Rtl, Excluded),
+    "Serif Old Uyghur" => OldUyghur("Old Uyghur", "Ougr", Rtl, Excluded),
+    "Sans Oriya" | "Serif Oriya" => Oriya("Oriya", "Orya", Ltr, Recommended),
+    "Sans Osage" => Osage("Osage", "Osge", Ltr, LimitedUse),
+    "Sans Osmanya" => Osmanya("Osmanya", "Osma", Ltr, Excluded),
+    "Serif Ottoman Siyaq" => OttomanSiyaq("Ottoman Siyaq", "Zyyy", Rtl, Excluded),
+    "Sans Pahawh Hmong" => PahawhHmong("Pahawh Hmong", "Hmng", Ltr, Excluded),
+    "Sans Palmyrene" => Palmyrene("Palmyrene", "Palm", Rtl, Excluded),
+    "Sans PauCinHau" => PauCinHau("Pau Cin Hau", "Pauc", Ltr, Excluded),
+    "Sans PhagsPa" => PhagsPa("Phags-Pa", "Phag", Ttb, Excluded),
+    "Sans Phoenician" => Phoenician("Phoenician", "Phnx", Rtl, Excluded),
+    "Sans PsaPahlavi" => PsalterPahlavi("Psalter Pahlavi", "Phlp", Rtl, Excluded),
+    "Sans Rejang" => Rejang("Rejang", "Rjng", Ltr, Excluded),
+    "Sans Runic" => Runic("Runic", "Runr", Ltr, Excluded),
+    "Sans Samaritan" => Samaritan("Samaritan", "Samr", Rtl, Excluded),
+    "Sans Saurashtra" => Saurashtra("Saurashtra", "Saur", Ltr, LimitedUse),
+    "Sans Sharada" => Sharada("Sharada", "Shrd", Ltr, Excluded),
+    "Sans Shavian" => Shavian("Shavian", "Shaw", Ltr, Excluded),
+    "Sans Siddham" => Siddham("Siddham", "Sidd", Ltr, Excluded),
+    "Sans SignWriting" => SignWriting("SignWriting", "Sgnw", Ltr, Excluded),
+    "Sans Sinhala" | "Serif Sinhala" => Sinhala("Sinhala", "Sinh", Ltr, Recommended),
+    "Sans Sogdian" => Sogdian("Sogdian", "Sogd", Rtl, Excluded),
+    "Sans Sora Sompeng" => SoraSompeng("Sora Sompeng", "Sora", Ltr, Excluded),
+    "Sans Soyombo" => Soyombo("Soyombo", "Soyo", Ltr, Excluded),
+    "Sans Sundanese" => Sundanese("Sundanese", "Sund", Ltr, LimitedUse),
+    "Sans Sunuwar" => Sunuwar("Sunuwar", "Sunu", Ltr, LimitedUse),
+    "Sans Syloti Nagri" => SylotiNagri("Syloti Nagri", "Sylo", Ltr, LimitedUse),
+    "Sans Symbols" => Symbols("Symbols", "Zsym", Ltr, Recommended),
+    // there are only ~15 characters that both support
+    "Sans Symbols 2" => Symbols2("Symbols 2", "Zsym", Ltr, Recommended),
+    "Sans Syriac"
+    | "Sans Syriac Eastern"
+    | "Sans Syriac Western" => Syriac("Syriac", "Syrc", Rtl, LimitedUse),
+    "Sans Tamil" | "Serif Tamil" => Tamil("Tamil", "Taml", Ltr, Recommended),
+    "Sans Tamil Supplement" => TamilSupplement("Tamil Supplement", "Taml", Ltr, Excluded),
+    "Sans Tagalog" => Tagalog("Tagalog", "Tglg", Ltr, Excluded),
+    "Sans Tagbanwa" => Tagbanwa("Tagbanwa", "Tagb", Ltr, Excluded),
+    "Sans Tai Le" => TaiLe("Tai Le", "Tale", Ltr, LimitedUse),
+    "Sans Tai Tham" => TaiTham("Tai Tham", "Lana", Ltr, LimitedUse),
+    "Sans Tai Viet" => TaiViet("Tai Viet", "Tavt", Ltr, LimitedUse),
+    "Sans Takri" => Takri("Takri", "Takr", Ltr, Excluded),
+    "Sans Tangsa" => Tangsa("Tangsa", "Tnsa", Ltr, LimitedUse),
+    "Serif Tangut" => Tangut("Tangut", "Tang", Ltr, Excluded),
+    "Sans Telugu" | "Serif Telugu" => Telugu("Telugu", "Telu", Ltr, Recommended),
+    "Sans Thaana" => Thaana("Thaana", "Thaa", Rtl, Recommended),
+    "Sans Thai" | "Sans Thai Looped" | "Serif Thai" => Thai("Thai", "Thai", Ltr, Recommended),
+    "Serif Tibetan" => Tibetan("Tibetan", "Tibt", Ltr, Recommended),
     // i have no clue what these variants are
     "Sans Tifinagh"
     | "Sans Tifinagh APT"
@@ -489,34 +2301,514 @@ generate_script! {
     | "Sans Tifinagh Hawad"
     | "Sans Tifinagh Rhissa Ixa"
     | "Sans Tifinagh SIL"
-    | "Sans Tifinagh Tawellemmet" => "Tifinagh",
-    "Sans Tirhuta" => "Tirhuta",
-    "Serif Toto" => "Toto",
-    "Sans Ugaritic" => "Ugaritic",
-    "Sans Vai" => "Vai",
-    "Sans Vithkuqi" | "Serif Vithkuqi" => "Vithkuqi",
-    "Sans Wancho" => "Wancho",
-    "Sans WarangCiti" => "Warang Citi",
-    "Serif Yezidi" => "Yezidi",
-    "Sans Yi" => "Yi",
-    "Sans Zanabazar" => "Zanabazar",
-    "Znamenny Musical Notation" => "Znamenny Musical Notation",
-}
-
-pub fn scripts(fonts: &[String]) -> Vec<Script> {
+    | "Sans Tifinagh Tawellemmet" => Tifinagh("Tifinagh", "Tfng", Ltr, LimitedUse),
+    "Sans Tirhuta" => Tirhuta("Tirhuta", "Tirh", Ltr, Excluded),
+    "Serif Toto" => Toto("Toto", "Toto", Ltr, LimitedUse),
+    "Sans Ugaritic" => Ugaritic("Ugaritic", "Ugar", Ltr, Excluded),
+    "Sans Vai" => Vai("Vai", "Vaii", Ltr, LimitedUse),
+    "Sans Vithkuqi" | "Serif Vithkuqi" => Vithkuqi("Vithkuqi", "Vith", Ltr, Excluded),
+    "Sans Wancho" => Wancho("Wancho", "Wcho", Ltr, LimitedUse),
+    "Sans WarangCiti" => WarangCiti("Warang Citi", "Wara", Ltr, Excluded),
+    "Serif Yezidi" => Yezidi("Yezidi", "Yezi", Rtl, Excluded),
+    "Sans Yi" => Yi("Yi", "Yiii", Ltr, LimitedUse),
+    "Sans Zanabazar" => Zanabazar("Zanabazar", "Zanb", Ltr, Excluded),
+    "Znamenny Musical Notation" =>
+        ZnamennyMusicalNotation("Znamenny Musical Notation", "Zinh", Ltr, Excluded),
+}
+
+/// how a script is used nowadays, after UAX #31 and CLDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptStatus {
+    /// in widespread everyday use
+    Recommended,
+    /// in everyday use by smaller communities
+    LimitedUse,
+    /// historic, liturgical or otherwise not in everyday use
+    Excluded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    /// top to bottom, like mongolian
+    Ttb,
+}
+
+impl Script {
+    /// the value of the Unicode Script property for the script's characters
+    pub fn unicode_script(&self) -> UnicodeScriptValue {
+        UnicodeScriptValue::from_short_name(self.iso15924()).unwrap_or(UnicodeScriptValue::Common)
+    }
+
+    /// the script whose fonts are for a character, going by its Unicode Script property. `None`
+    /// for Common and Inherited characters, which several scripts' fonts cover
+    pub fn from_char(c: char) -> Option<Self> { Self::from_unicode(c.script()) }
+
+    /// the script whose fonts are for a Unicode Script property value. `None` for Common,
+    /// Inherited and scripts that have no fonts in the table
+    pub fn from_unicode(script: UnicodeScriptValue) -> Option<Self> {
+        match script {
+            UnicodeScriptValue::Latin
+            | UnicodeScriptValue::Greek
+            | UnicodeScriptValue::Cyrillic => Some(Self::LatinGreekCyrillic),
+            UnicodeScriptValue::Han
+            | UnicodeScriptValue::Hiragana
+            | UnicodeScriptValue::Katakana
+            | UnicodeScriptValue::Hangul
+            | UnicodeScriptValue::Bopomofo => Some(Self::Cjk),
+            s => Self::ALL
+                .iter()
+                .find(|x| !x.iso15924().starts_with('Z') && x.iso15924() == s.short_name())
+                .cloned(),
+        }
+    }
+}
+
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+pub fn scripts(fonts: &[FontFamily]) -> Vec<Script> {
     fonts
         .iter()
-        .map(String::as_str)
-        .map(script)
-        .sorted_by_key(|f| f.0.to_lowercase())
+        .map(FontFamily::script)
+        .sorted_by_key(|f| f.name().to_lowercase())
         .dedup()
         .collect_vec()
 }
 
-fn missing_variants(font_names: &[String]) -> Vec<String> {
+/// the Unicode Script property value most of the codepoints have, leaving out Common and
+/// Inherited. `None` if that's all there is
+pub fn dominant_script(codepoints: &[u32]) -> Option<UnicodeScriptValue> {
+    codepoints
+        .iter()
+        .filter_map(|c| char::from_u32(*c))
+        .map(|c| c.script())
+        .filter(|s| {
+            ![
+                UnicodeScriptValue::Common,
+                UnicodeScriptValue::Inherited,
+                UnicodeScriptValue::Unknown,
+            ]
+            .contains(s)
+        })
+        .counts()
+        .into_iter()
+        .max_by_key(|(s, n)| (*n, std::cmp::Reverse(s.short_name())))
+        .map(|(s, _)| s)
+}
+
+fn missing_variants(font_names: &[FontFamily]) -> Vec<FontFamily> {
     all_variants()
         .iter()
-        .filter(|v| font_names.iter().any(|f| script(f) == script(v)) && !font_names.contains(v))
+        .filter(|v| font_names.iter().any(|f| f.script() == v.script()) && !font_names.contains(v))
         .cloned()
         .collect_vec()
 }
+
+const DOTTED_CIRCLE: u32 = 0x25cc;
+const ZWNJ: u32 = 0x200c;
+const ZWJ: u32 = 0x200d;
+
+/// characters a script's shaping engine needs from the font even though they rarely appear in
+/// the text itself
+pub fn shaping_support(script: &Script) -> &'static [u32] {
+    match script {
+        // indic and other brahmic scripts
+        Script::Ahom
+        | Script::Balinese
+        | Script::Batak
+        | Script::Bhaiksuki
+        | Script::Brahmi
+        | Script::Buginese
+        | Script::Buhid
+        | Script::Chakma
+        | Script::Cham
+        | Script::DivesAkuru
+        | Script::Dogra
+        | Script::Grantha
+        | Script::Hanunoo
+        | Script::Javanese
+        | Script::Kaithi
+        | Script::Kawi
+        | Script::Kharoshthi
+        | Script::Khmer
+        | Script::Khojki
+        | Script::Khudawadi
+        | Script::Lao
+        | Script::Lepcha
+        | Script::Limbu
+        | Script::Mahajani
+        | Script::Makasar
+        | Script::Marchen
+        | Script::MeeteiMayek
+        | Script::Modi
+        | Script::Multani
+        | Script::Myanmar
+        | Script::Nandinagari
+        | Script::NewTaiLue
+        | Script::Newa
+        | Script::Rejang
+        | Script::Saurashtra
+        | Script::Sharada
+        | Script::Siddham
+        | Script::Sinhala
+        | Script::Soyombo
+        | Script::Sundanese
+        | Script::SylotiNagri
+        | Script::Tagalog
+        | Script::Tagbanwa
+        | Script::TaiLe
+        | Script::TaiTham
+        | Script::TaiViet
+        | Script::Takri
+        | Script::Thai
+        | Script::Tibetan
+        | Script::Tirhuta
+        | Script::Zanabazar => &[ZWNJ, ZWJ, DOTTED_CIRCLE],
+        // ...that also share the devanagari dandas
+        Script::Bengali
+        | Script::Devanagari
+        | Script::Gujarati
+        | Script::Gurmukhi
+        | Script::Kannada
+        | Script::Malayalam
+        | Script::Oriya
+        | Script::Tamil
+        | Script::Telugu => &[0x0964, 0x0965, ZWNJ, ZWJ, DOTTED_CIRCLE],
+        // joining scripts that stretch with the arabic tatweel
+        Script::Adlam
+        | Script::Arabic
+        | Script::HanifiRohingya
+        | Script::Mandaic
+        | Script::Manichaean
+        | Script::OldUyghur
+        | Script::PsalterPahlavi
+        | Script::Sogdian
+        | Script::Syriac => &[0x0640, ZWNJ, ZWJ, DOTTED_CIRCLE],
+        Script::NKo => &[ZWNJ, ZWJ, DOTTED_CIRCLE],
+        // free variation selectors, vowel separator and the narrow nbsp
+        Script::Mongolian => {
+            &[0x180b, 0x180c, 0x180d, 0x180e, 0x180f, 0x202f, ZWNJ, ZWJ, DOTTED_CIRCLE]
+        }
+        Script::PhagsPa => &[0x1802, 0x1803, 0x1805, 0x202f, ZWNJ, ZWJ, DOTTED_CIRCLE],
+        // scripts with combining marks
+        Script::Hebrew | Script::Samaritan | Script::Thaana | Script::Tifinagh => {
+            &[ZWJ, DOTTED_CIRCLE]
+        }
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a stack of some families, each supporting the codepoints after it
+    fn stack(fonts: &[(&str, &[u32])]) -> FontStack {
+        let mut map = HashMap::<u32, Vec<FontFamily>>::new();
+        for (name, cps) in fonts {
+            for c in *cps {
+                map.entry(*c).or_default().push(FontFamily::new(name));
+            }
+        }
+        FontStack {
+            names: fonts.iter().map(|(name, _)| FontFamily::new(name)).collect(),
+            codepoints: map.keys().copied().sorted().collect(),
+            map,
+            invisible: vec![],
+            normalized: vec![],
+            over_budget: vec![],
+            historic: vec![],
+            no_vertical_features: vec![],
+            unverified: vec![],
+            critical: vec![],
+        }
+    }
+
+    /// Sans, Serif, CJK, and Devanagari in both classes
+    fn mixed() -> (FontStack, Vec<Font>) {
+        let stack = stack(&[
+            ("Sans", &[0x41, 0x42, 0x43]),
+            ("Serif", &[0x41, 0x44]),
+            ("Sans CJK JP", &[0x3042, 0x3044, 0x4e00]),
+            ("Sans Devanagari", &[0x915, 0x916, 0x917, 0x94d]),
+            ("Serif Devanagari", &[0x915, 0x918]),
+        ]);
+        // written as woff2, so the names differ from upstream's
+        let files = stack
+            .names
+            .iter()
+            .map(|f| Font {
+                filename: format!("{}.woff2", f.postscript_prefix()),
+                fontname: f.clone(),
+                bytes: vec![],
+            })
+            .collect();
+        (stack, files)
+    }
+
+    fn tracked(name: &str, file: &str) -> Font {
+        let path = format!("{}/out/fonts/{file}", env!("CARGO_MANIFEST_DIR"));
+        Font {
+            filename: file.into(),
+            fontname: FontFamily::new(name),
+            bytes: std::fs::read(path).unwrap(),
+        }
+    }
+
+    #[test]
+    fn noto_prefix() {
+        let json = br#"{"fonts": {"Noto Sans Adlam": {
+            "owner": "notofonts", "repo": "adlam", "path": "a.ttf", "format": "ttf"
+        }}}"#;
+        let manifest = Manifest::from_json(json).unwrap();
+        let adlam = FontFamily::new("Sans Adlam");
+        assert_eq!(manifest.resolve(&adlam).path, "a.ttf");
+        let (family, _) = manifest.fonts.into_iter().next().unwrap();
+        assert_eq!(family.css_name(), "Noto Sans Adlam");
+    }
+
+    #[test]
+    fn slices() {
+        let joiners = [ZWNJ, ZWJ, DOTTED_CIRCLE];
+        let stack = stack(&[
+            ("Sans", &[0x41, ZWNJ, ZWJ, DOTTED_CIRCLE]),
+            ("Sans Devanagari", &[0x915, 0x937, 0x94d, ZWNJ, ZWJ, DOTTED_CIRCLE]),
+        ]);
+        let files = [
+            tracked("Sans", "NotoSans-Regular.ttf"),
+            tracked("Sans Devanagari", "NotoSansDevanagari-Regular.ttf"),
+        ];
+        let slices = stack.slices(&files, 1, None);
+        let all = slices.iter().flat_map(|s| s.codepoints.clone()).sorted().collect_vec();
+        assert_eq!(all, stack.codepoints);
+        for slice in slices.iter().filter(|s| s.font.fontname == files[1].fontname) {
+            assert!(!slice.codepoints.iter().any(|c| joiners.contains(c)));
+            let face = ttf_parser::Face::parse(&slice.font.bytes, 0).unwrap();
+            let mapped = |c| char::from_u32(c).and_then(|c| face.glyph_index(c)).is_some();
+            assert!(joiners.into_iter().all(mapped));
+        }
+        assert!(stack.stylesheet("", &files).contains("unicode-range: U+915, U+937, U+94d;"));
+    }
+
+    #[test]
+    fn unicode_ranges() {
+        assert_eq!(unicode_range(&[0x41, 0x42, 0x43, 0x45, 0x1f600]), "U+41-43, U+45, U+1f600");
+        assert_eq!(unicode_range(&[]), "");
+    }
+
+    #[test]
+    fn stylesheet() {
+        let (stack, files) = mixed();
+        assert_eq!(
+            stack.stylesheet("/f/", &files),
+            concat!(
+                r#"@font-face {
+  font-family: "Noto Sans";
+  src: url("/f/NotoSans.woff2") format("woff2");
+  unicode-range: U+41-43;
+}
+@font-face {
+  font-family: "Noto Serif";
+  src: url("/f/NotoSerif.woff2") format("woff2");
+  unicode-range: U+44;
+}
+@font-face {
+  font-family: "Noto Sans CJK JP";
+  src: url("/f/NotoSansCJKjp.woff2") format("woff2");
+  unicode-range: U+3042, U+3044, U+4e00;
+}
+@font-face {
+  font-family: "Noto Sans Devanagari";
+  src: url("/f/NotoSansDevanagari.woff2") format("woff2");
+  unicode-range: U+915-917, U+94d;
+}
+@font-face {
+  font-family: "Noto Serif Devanagari";
+  src: url("/f/NotoSerifDevanagari.woff2") format("woff2");
+  unicode-range: U+918;
+}
+:root {
+  --notoize: "Noto Sans", "Noto Serif", "Noto Sans CJK JP", "#,
+                r#""Noto Sans Devanagari", "Noto Serif Devanagari";
+}
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn fontconfig() {
+        let (stack, _) = mixed();
+        assert_eq!(
+            stack.fontconfig("/fonts", true),
+            r#"<?xml version="1.0"?>
+<!DOCTYPE fontconfig SYSTEM "urn:fontconfig:fonts.dtd">
+<fontconfig>
+  <dir>/fonts</dir>
+  <alias>
+    <family>sans-serif</family>
+    <prefer>
+      <family>Noto Sans</family>
+      <family>Noto Sans CJK JP</family>
+      <family>Noto Sans Devanagari</family>
+    </prefer>
+    <accept>
+      <family>Noto Serif</family>
+      <family>Noto Serif Devanagari</family>
+    </accept>
+  </alias>
+  <alias>
+    <family>serif</family>
+    <prefer>
+      <family>Noto Serif</family>
+      <family>Noto Serif Devanagari</family>
+    </prefer>
+    <accept>
+      <family>Noto Sans</family>
+      <family>Noto Sans CJK JP</family>
+      <family>Noto Sans Devanagari</family>
+    </accept>
+  </alias>
+  <alias>
+    <family>monospace</family>
+    <accept>
+      <family>Noto Sans</family>
+      <family>Noto Serif</family>
+      <family>Noto Sans CJK JP</family>
+      <family>Noto Sans Devanagari</family>
+      <family>Noto Serif Devanagari</family>
+    </accept>
+  </alias>
+  <match target="pattern">
+    <test name="lang" compare="contains"><string>ja</string></test>
+    <edit name="family" mode="prepend" binding="strong"><string>Noto Sans CJK JP</string></edit>
+  </match>
+</fontconfig>
+"#
+        );
+    }
+
+    #[test]
+    fn terminal_config() {
+        let (stack, _) = mixed();
+        assert_eq!(
+            stack.terminal_config(Terminal::Kitty),
+            r#"font_family Noto Sans Mono
+symbol_map U+0044 Noto Serif
+symbol_map U+3042,U+3044,U+4E00 Noto Sans CJK JP
+symbol_map U+0915-U+0917,U+094D Noto Sans Devanagari
+symbol_map U+0918 Noto Serif Devanagari
+"#
+        );
+        assert_eq!(
+            stack.terminal_config(Terminal::WezTerm),
+            r#"config.font = wezterm.font_with_fallback {
+  'Noto Sans Mono',
+  'Noto Serif',
+  'Noto Sans CJK JP',
+  'Noto Sans Devanagari',
+  'Noto Serif Devanagari',
+}
+"#
+        );
+    }
+
+    #[test]
+    fn typst() {
+        let (stack, _) = mixed();
+        assert_eq!(
+            stack.typst(),
+            concat!(
+                r##"#set text(font: ("Noto Sans", "Noto Serif", "Noto Sans CJK JP", "##,
+                r##""Noto Sans Devanagari", "Noto Serif Devanagari"))"##,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn latex() {
+        let (stack, files) = mixed();
+        assert_eq!(
+            stack.latex("fonts", &files),
+            r#"\usepackage{fontspec}
+\usepackage{iftex}
+\newfontfamily\notosans{NotoSans.woff2}[Path=fonts/]
+\newfontfamily\notoserif{NotoSerif.woff2}[Path=fonts/]
+\newfontfamily\notosanscjkjp{NotoSansCJKjp.woff2}[Path=fonts/]
+\newfontfamily\notosansdevanagari{NotoSansDevanagari.woff2}[Path=fonts/]
+\newfontfamily\notoserifdevanagari{NotoSerifDevanagari.woff2}[Path=fonts/]
+\ifluatex
+  \directlua{luaotfload.add_fallback("notoize", {
+    "[fonts/NotoSerif.woff2]:mode=harf;",
+    "[fonts/NotoSansCJKjp.woff2]:mode=harf;",
+    "[fonts/NotoSansDevanagari.woff2]:mode=harf;",
+    "[fonts/NotoSerifDevanagari.woff2]:mode=harf;",
+  })}
+  \setmainfont{NotoSans.woff2}[Path=fonts/, RawFeature={fallback=notoize}]
+\else
+  \setmainfont{NotoSans.woff2}[Path=fonts/]
+  \usepackage{ucharclasses}
+  \setTransitionsFor{Devanagari}{\notosansdevanagari}{\normalfont}
+  \setTransitionsFor{Hiragana}{\notosanscjkjp}{\normalfont}
+  \setTransitionsFor{CJKUnifiedIdeographs}{\notosanscjkjp}{\normalfont}
+\fi
+"#
+        );
+    }
+
+    #[test]
+    fn android_fonts_xml() {
+        let (stack, files) = mixed();
+        assert_eq!(
+            stack.android_fonts_xml(&files),
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<familyset version="23">
+    <family name="sans-serif">
+        <font weight="400" style="normal">NotoSans.woff2</font>
+    </family>
+    <family name="serif">
+        <font weight="400" style="normal">NotoSerif.woff2</font>
+    </family>
+    <family lang="ja">
+        <font weight="400" style="normal">NotoSansCJKjp.woff2</font>
+    </family>
+    <family lang="und-Deva">
+        <font weight="400" style="normal">NotoSansDevanagari.woff2</font>
+        <font weight="400" style="normal" fallbackFor="serif">NotoSerifDevanagari.woff2</font>
+    </family>
+</familyset>
+"#
+        );
+    }
+
+    #[test]
+    fn select_by_size() {
+        let (sans, serif, deva) =
+            (FontFamily::new("Sans"), FontFamily::new("Serif"), FontFamily::new("Sans Devanagari"));
+        let client = NotoizeClient {
+            blocks: vec![],
+            font_support: HashMap::from([
+                (0x41, vec![sans.clone(), serif.clone()]),
+                (0x42, vec![sans.clone(), serif.clone()]),
+                (0x3b1, vec![sans.clone()]),
+                (0x915, vec![deva.clone()]),
+            ]),
+            vertical_features: HashMap::new(),
+            manifest: Manifest::default(),
+        };
+        let mut manifest = Manifest::default();
+        for (f, size) in [(&sans, 300), (&serif, 100), (&deva, 50)] {
+            let source = FontSource { size: Some(size), ..builtin_source(f) };
+            manifest.fonts.insert(f.clone(), source);
+        }
+        let config = NotoizeConfig { budget: Some(200), ..NotoizeConfig::default() };
+        let (fonts, left) =
+            client.select_by_size(&[0x41, 0x42, 0x3b1, 0x915], &config, &manifest.sizes());
+        assert_eq!(fonts.into_iter().sorted().collect_vec(), [deva, serif]);
+        assert_eq!(left, [0x3b1]);
+    }
+}
@@ -1,8 +1,23 @@
-use std::{fs, sync::LazyLock, time::Instant};
+use std::{env, fs, path::Path, process::ExitCode, sync::LazyLock, time::Instant};
 
-use notoize::NotoizeClient;
+use itertools::Itertools;
+use notoize::{
+    all_variants, dominant_script, FontFamily, FontSource, Manifest, NotoizeClient, Script,
+};
+use unicode_script::UnicodeScript as _;
 
-fn main() {
+/// how much of a family's coverage the most common script has to be to count as the family's.
+/// symbol and math fonts have some letters, but they aren't for those scripts
+const MIN_SHARE: f64 = 1.0 / 3.0;
+
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("validate") => return validate(args.get(2).map(Path::new)),
+        Some("check") => return check(),
+        Some("table") => return table(),
+        _ => {}
+    }
     static ALL: LazyLock<String> =
         LazyLock::new(|| (0..0x11_0000).filter_map(char::from_u32).collect::<String>());
     let start = Instant::now();
@@ -14,10 +29,158 @@ fn main() {
     fs::write("out/data/mapping.txt", map.all).unwrap();
     fs::write("out/data/script_conflicts.txt", map.conflicts).unwrap();
     fs::write("out/data/missing_variants.txt", map.missing).unwrap();
+    // fetched first, so the old fonts are still there if upstream moved one
+    let files = match the.try_files_from(&Manifest::builtin()) {
+        Ok(files) => files,
+        Err(e) => {
+            println!("\x1b[2K\x1b[G{e}");
+            return ExitCode::FAILURE;
+        }
+    };
     let _ = fs::remove_dir_all("out/fonts");
     fs::create_dir_all("out/fonts").unwrap();
-    for font in the.files() {
+    for font in files {
         fs::write(format!("out/fonts/{}", font.filename), font.bytes).unwrap();
     }
     println!(" in \x1b[92m{:?}\x1b[m", start.elapsed());
+    ExitCode::SUCCESS
+}
+
+/// lists the families upstream has that the script table doesn't
+fn check() -> ExitCode {
+    let unknown = NotoizeClient::new().self_check();
+    if unknown.is_empty() {
+        println!("\x1b[2K\x1b[Gevery family is in the table");
+        return ExitCode::SUCCESS;
+    }
+    println!("\x1b[2K\x1b[Gnot in the table:");
+    for f in unknown {
+        println!("    \x1b[91m{f}\x1b[m");
+    }
+    ExitCode::FAILURE
+}
+
+/// regenerates the `generate_script!` table from what each family covers, going by Scripts.txt,
+/// into out/data/script_table.rs and lists the families that disagree with the current table.
+/// families keep their current row if their script agrees with it, if no script has enough of
+/// their coverage, or if there's no coverage data
+fn table() -> ExitCode {
+    let coverage = NotoizeClient::new().coverage();
+    let families = all_variants()
+        .into_iter()
+        .chain(
+            coverage.keys().filter(|f| matches!(f.script(), Script::Unknown(_))).sorted().cloned(),
+        )
+        .collect_vec();
+    let mut rows = Vec::<(String, Vec<FontFamily>)>::new();
+    let mut disagreements = vec![];
+    for f in families {
+        let current = f.script();
+        let Some(cps) = coverage.get(&f) else {
+            let current_row = row(&current);
+            match rows.iter_mut().find(|(r, _)| *r == current_row) {
+                Some((_, fonts)) => fonts.push(f),
+                None => rows.push((current_row, vec![f])),
+            }
+            continue;
+        };
+        let derived = dominant_script(cps).filter(|s| {
+            let n = cps.iter().filter_map(|c| char::from_u32(*c)).filter(|c| c.script() == *s);
+            n.count() as f64 >= MIN_SHARE * cps.len() as f64
+        });
+        // split-out families like Tamil Supplement have the script of the main one
+        let agrees =
+            |s| current.unicode_script() == s || Script::from_unicode(s).as_ref() == Some(&current);
+        let derived_row = match derived.map(|s| (s, Script::from_unicode(s))) {
+            // a placeholder of its own, so it doesn't clash with `Unknown` or other families
+            None if matches!(current, Script::Unknown(_)) => format!(
+                "{}({:?}, \"Zyyy\", Ltr, Excluded), // no script of its own, name it by hand",
+                f.name().chars().filter(char::is_ascii_alphanumeric).collect::<String>(),
+                f.name()
+            ),
+            // symbols, math, music and such have no script of their own
+            None => row(&current),
+            Some((s, _)) if agrees(s) => row(&current),
+            Some((_, Some(s))) => row(&s),
+            Some((s, None)) => {
+                let name = s.full_name().replace('_', " ");
+                format!(
+                    "{}(\"{name}\", \"{}\", Ltr, Excluded), // new, check direction and status",
+                    name.replace(' ', ""),
+                    s.short_name()
+                )
+            }
+        };
+        if derived.is_some_and(|s| !agrees(s)) {
+            disagreements.push((f.clone(), current, derived_row.clone()));
+        }
+        match rows.iter_mut().find(|(r, _)| *r == derived_row) {
+            Some((_, fonts)) => fonts.push(f),
+            None => rows.push((derived_row, vec![f])),
+        }
+    }
+    let table = rows
+        .into_iter()
+        .map(|(row, fonts)| {
+            let uncovered = fonts.iter().filter(|f| !coverage.contains_key(f)).collect_vec();
+            let comment = if uncovered.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "    // no coverage data, kept where the table has them: {}\n",
+                    uncovered.iter().map(|f| format!("{:?}", f.name())).join(", ")
+                )
+            };
+            format!(
+                "{comment}    {} => {row}\n",
+                fonts.iter().map(|f| format!("{:?}", f.name())).join(" | ")
+            )
+        })
+        .collect::<String>();
+    fs::create_dir_all("out/data").unwrap();
+    fs::write("out/data/script_table.rs", format!("generate_script! {{\n{table}}}\n")).unwrap();
+    if disagreements.is_empty() {
+        println!("\x1b[2K\x1b[Gthe table agrees with the coverage");
+        return ExitCode::SUCCESS;
+    }
+    println!("\x1b[2K\x1b[Gdisagreements:");
+    for (f, current, derived) in disagreements {
+        println!("    \x1b[91m{f}\x1b[m is {current:?} in the table but derives as {derived}");
+    }
+    ExitCode::FAILURE
+}
+
+/// a script's right-hand side in the `generate_script!` table
+fn row(s: &Script) -> String {
+    format!(
+        "{}({:?}, {:?}, {:?}, {:?}),",
+        format!("{s:?}").split('(').next().unwrap(),
+        s.name(),
+        s.iso15924(),
+        s.direction(),
+        s.status()
+    )
+}
+
+/// checks that every family resolves to a font file, on github or in a folder with the repos
+/// checked out
+fn validate(root: Option<&Path>) -> ExitCode {
+    let manifest = match fs::read("manifest.json") {
+        Ok(json) => Manifest::builtin().merge(Manifest::from_json(&json).unwrap()),
+        Err(_) => Manifest::builtin(),
+    };
+    let missing = match root {
+        Some(root) => manifest.validate(|s| s.exists_in(root)),
+        None => manifest.validate(FontSource::exists_on_github),
+    };
+    if missing.is_empty() {
+        println!("\x1b[2K\x1b[Gall families resolved");
+        return ExitCode::SUCCESS;
+    }
+    println!("\x1b[2K\x1b[Gcould not find:");
+    for f in missing {
+        let s = manifest.resolve(&f);
+        println!("    \x1b[91m{f}\x1b[m at {}/{}/{}", s.owner, s.repo, s.path);
+    }
+    ExitCode::FAILURE
 }
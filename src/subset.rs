@@ -0,0 +1,524 @@
+//! cutting `cmap` and the layout tables down to the glyphs `subsetter` kept. it keeps glyph ids as
+//! they are, so nothing gets renumbered but classes
+
+use std::collections::BTreeSet;
+
+use write_fonts::{
+    dump_table,
+    from_obj::ToOwnedTable as _,
+    read::{FontRef, TableProvider as _},
+    tables::{
+        cmap::Cmap,
+        gdef::Gdef,
+        gpos::{
+            CursivePosFormat1, ExtensionSubtable as PosExtension, Gpos, MarkArray,
+            MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1, PairPos, PositionLookup,
+            SinglePos,
+        },
+        gsub::{
+            AlternateSubstFormat1, ExtensionSubtable as SubstExtension, Gsub, LigatureSubstFormat1,
+            MultipleSubstFormat1, ReverseChainSingleSubstFormat1, SingleSubst, SubstitutionLookup,
+        },
+        layout::{ChainedSequenceContext, ClassDef, CoverageTable, Lookup, SequenceContext},
+    },
+    types::{GlyphId, GlyphId16},
+};
+
+/// tables that are copied over as they are
+const KEPT_TABLES: [[u8; 4]; 6] = [*b"BASE", *b"JSTF", *b"MATH", *b"kern", *b"vhea", *b"vmtx"];
+
+type Glyphs = BTreeSet<u16>;
+/// a table tag and its data
+type Table = ([u8; 4], Vec<u8>);
+
+/// a `cmap` with only the codepoints the font has, from `codepoints`
+pub(crate) fn cmap(font: &[u8], codepoints: &[u32]) -> Result<Vec<u8>, String> {
+    let face = ttf_parser::Face::parse(font, 0).map_err(|e| e.to_string())?;
+    let mappings = codepoints.iter().filter_map(|c| {
+        let c = char::from_u32(*c)?;
+        Some((c, GlyphId::new(face.glyph_index(c)?.0.into())))
+    });
+    let cmap = Cmap::from_mappings(mappings).map_err(|e| e.to_string())?;
+    dump_table(&cmap).map_err(|e| e.to_string())
+}
+
+/// GSUB, GPOS and GDEF without the glyphs that aren't kept, and the other layout tables as they
+/// are
+pub(crate) fn layout_tables(font: &[u8], glyphs: &Glyphs) -> Result<Vec<Table>, String> {
+    let f = FontRef::new(font).map_err(|e| e.to_string())?;
+    let err = |e: write_fonts::error::Error| e.to_string();
+    let mut tables = vec![];
+    if let Ok(gsub) = f.gsub() {
+        let mut gsub: Gsub = gsub.to_owned_table();
+        for lookup in &mut gsub.lookup_list.lookups {
+            prune_subst_lookup(lookup, glyphs);
+        }
+        tables.push((*b"GSUB", dump_table(&gsub).map_err(err)?));
+    }
+    if let Ok(gpos) = f.gpos() {
+        let mut gpos: Gpos = gpos.to_owned_table();
+        for lookup in &mut gpos.lookup_list.lookups {
+            prune_pos_lookup(lookup, glyphs);
+        }
+        tables.push((*b"GPOS", dump_table(&gpos).map_err(err)?));
+    }
+    if let Ok(gdef) = f.gdef() {
+        let mut gdef: Gdef = gdef.to_owned_table();
+        prune_gdef(&mut gdef, glyphs);
+        tables.push((*b"GDEF", dump_table(&gdef).map_err(err)?));
+    }
+    let raw = ttf_parser::RawFace::parse(font, 0).map_err(|e| e.to_string())?;
+    tables.extend(
+        KEPT_TABLES
+            .iter()
+            .filter_map(|tag| Some((*tag, raw.table(ttf_parser::Tag::from_bytes(tag))?.to_vec()))),
+    );
+    Ok(tables)
+}
+
+fn keep(glyphs: &Glyphs, g: GlyphId16) -> bool { glyphs.contains(&g.to_u16()) }
+
+/// keeps the items for kept glyphs that `f` keeps too, and their glyphs in the coverage. returns
+/// whether any are left
+fn retain_covered<T>(
+    coverage: &mut CoverageTable,
+    items: &mut Vec<T>,
+    glyphs: &Glyphs,
+    mut f: impl FnMut(&mut T) -> bool,
+) -> bool {
+    let mut kept = vec![];
+    let old = std::mem::take(items);
+    for (g, mut item) in coverage.iter().zip(old) {
+        if keep(glyphs, g) && f(&mut item) {
+            kept.push(g);
+            items.push(item);
+        }
+    }
+    *coverage = kept.into();
+    !items.is_empty()
+}
+
+/// keeps the kept glyphs in the coverage. returns whether any are left
+fn prune_coverage(coverage: &mut CoverageTable, glyphs: &Glyphs) -> bool {
+    let mut items = vec![(); coverage.len()];
+    retain_covered(coverage, &mut items, glyphs, |()| true)
+}
+
+/// the class definition without the glyphs that aren't kept
+fn prune_class_def(class_def: &ClassDef, glyphs: &Glyphs) -> ClassDef {
+    class_def.iter().filter(|(g, c)| *c != 0 && keep(glyphs, *g)).collect()
+}
+
+/// the class definition with only the `used` classes (which include 0), numbered in order
+fn renumber(class_def: &ClassDef, used: &BTreeSet<u16>, glyphs: &Glyphs) -> ClassDef {
+    class_def
+        .iter()
+        .filter(|(g, c)| *c != 0 && keep(glyphs, *g))
+        .filter_map(|(g, c)| Some((g, used.iter().position(|u| *u == c)? as u16)))
+        .collect()
+}
+
+/// keeps the items at the `used` positions
+fn pick<T>(items: &mut Vec<T>, used: &BTreeSet<u16>) {
+    let old = std::mem::take(items);
+    items.extend(
+        old.into_iter().enumerate().filter(|(i, _)| used.contains(&(*i as u16))).map(|(_, x)| x),
+    );
+}
+
+fn retain_subtables<T>(lookup: &mut Lookup<T>, mut f: impl FnMut(&mut T) -> bool) {
+    lookup.subtables.retain_mut(|s| f(s));
+}
+
+fn prune_subst_lookup(lookup: &mut SubstitutionLookup, glyphs: &Glyphs) {
+    match lookup {
+        SubstitutionLookup::Single(l) => retain_subtables(l, |s| prune_single_subst(s, glyphs)),
+        SubstitutionLookup::Multiple(l) => retain_subtables(l, |s| prune_multiple(s, glyphs)),
+        SubstitutionLookup::Alternate(l) => retain_subtables(l, |s| prune_alternate(s, glyphs)),
+        SubstitutionLookup::Ligature(l) => retain_subtables(l, |s| prune_ligature(s, glyphs)),
+        SubstitutionLookup::Contextual(l) => {
+            retain_subtables(l, |s| prune_sequence_context(s, glyphs));
+        }
+        SubstitutionLookup::ChainContextual(l) => {
+            retain_subtables(l, |s| prune_chained_context(s, glyphs));
+        }
+        SubstitutionLookup::Reverse(l) => retain_subtables(l, |s| prune_reverse(s, glyphs)),
+        SubstitutionLookup::Extension(l) => retain_subtables(l, |s| match s {
+            SubstExtension::Single(e) => prune_single_subst(&mut e.extension, glyphs),
+            SubstExtension::Multiple(e) => prune_multiple(&mut e.extension, glyphs),
+            SubstExtension::Alternate(e) => prune_alternate(&mut e.extension, glyphs),
+            SubstExtension::Ligature(e) => prune_ligature(&mut e.extension, glyphs),
+            SubstExtension::Contextual(e) => prune_sequence_context(&mut e.extension, glyphs),
+            SubstExtension::ChainContextual(e) => prune_chained_context(&mut e.extension, glyphs),
+            SubstExtension::Reverse(e) => prune_reverse(&mut e.extension, glyphs),
+        }),
+    }
+}
+
+fn prune_single_subst(s: &mut SingleSubst, glyphs: &Glyphs) -> bool {
+    match s {
+        SingleSubst::Format1(s) => prune_coverage(&mut s.coverage, glyphs),
+        SingleSubst::Format2(s) => {
+            retain_covered(&mut s.coverage, &mut s.substitute_glyph_ids, glyphs, |_| true)
+        }
+    }
+}
+
+fn prune_multiple(s: &mut MultipleSubstFormat1, glyphs: &Glyphs) -> bool {
+    retain_covered(&mut s.coverage, &mut s.sequences, glyphs, |_| true)
+}
+
+fn prune_alternate(s: &mut AlternateSubstFormat1, glyphs: &Glyphs) -> bool {
+    retain_covered(&mut s.coverage, &mut s.alternate_sets, glyphs, |_| true)
+}
+
+fn prune_ligature(s: &mut LigatureSubstFormat1, glyphs: &Glyphs) -> bool {
+    retain_covered(&mut s.coverage, &mut s.ligature_sets, glyphs, |set| {
+        set.ligatures.retain(|l| {
+            keep(glyphs, l.ligature_glyph) && l.component_glyph_ids.iter().all(|g| keep(glyphs, *g))
+        });
+        !set.ligatures.is_empty()
+    })
+}
+
+fn prune_reverse(s: &mut ReverseChainSingleSubstFormat1, glyphs: &Glyphs) -> bool {
+    s.backtrack_coverages.iter_mut().all(|c| prune_coverage(c, glyphs))
+        && s.lookahead_coverages.iter_mut().all(|c| prune_coverage(c, glyphs))
+        && retain_covered(&mut s.coverage, &mut s.substitute_glyph_ids, glyphs, |_| true)
+}
+
+fn prune_sequence_context(s: &mut SequenceContext, glyphs: &Glyphs) -> bool {
+    match s {
+        SequenceContext::Format1(s) => {
+            retain_covered(&mut s.coverage, &mut s.seq_rule_sets, glyphs, |set| {
+                set.as_mut().is_some_and(|set| {
+                    set.seq_rules.retain(|r| r.input_sequence.iter().all(|g| keep(glyphs, *g)));
+                    !set.seq_rules.is_empty()
+                })
+            })
+        }
+        SequenceContext::Format2(s) => {
+            s.class_def = prune_class_def(&s.class_def, glyphs).into();
+            prune_coverage(&mut s.coverage, glyphs)
+        }
+        SequenceContext::Format3(s) => s.coverages.iter_mut().all(|c| prune_coverage(c, glyphs)),
+    }
+}
+
+fn prune_chained_context(s: &mut ChainedSequenceContext, glyphs: &Glyphs) -> bool {
+    match s {
+        ChainedSequenceContext::Format1(s) => {
+            retain_covered(&mut s.coverage, &mut s.chained_seq_rule_sets, glyphs, |set| {
+                set.as_mut().is_some_and(|set| {
+                    set.chained_seq_rules.retain(|r| {
+                        [&r.backtrack_sequence, &r.input_sequence, &r.lookahead_sequence]
+                            .iter()
+                            .all(|seq| seq.iter().all(|g| keep(glyphs, *g)))
+                    });
+                    !set.chained_seq_rules.is_empty()
+                })
+            })
+        }
+        ChainedSequenceContext::Format2(s) => {
+            s.backtrack_class_def = prune_class_def(&s.backtrack_class_def, glyphs).into();
+            s.input_class_def = prune_class_def(&s.input_class_def, glyphs).into();
+            s.lookahead_class_def = prune_class_def(&s.lookahead_class_def, glyphs).into();
+            prune_coverage(&mut s.coverage, glyphs)
+        }
+        ChainedSequenceContext::Format3(s) => s
+            .backtrack_coverages
+            .iter_mut()
+            .chain(&mut s.input_coverages)
+            .chain(&mut s.lookahead_coverages)
+            .all(|c| prune_coverage(c, glyphs)),
+    }
+}
+
+fn prune_pos_lookup(lookup: &mut PositionLookup, glyphs: &Glyphs) {
+    match lookup {
+        PositionLookup::Single(l) => retain_subtables(l, |s| prune_single_pos(s, glyphs)),
+        PositionLookup::Pair(l) => retain_subtables(l, |s| prune_pair_pos(s, glyphs)),
+        PositionLookup::Cursive(l) => retain_subtables(l, |s| prune_cursive(s, glyphs)),
+        PositionLookup::MarkToBase(l) => retain_subtables(l, |s| prune_mark_base(s, glyphs)),
+        PositionLookup::MarkToLig(l) => retain_subtables(l, |s| prune_mark_lig(s, glyphs)),
+        PositionLookup::MarkToMark(l) => retain_subtables(l, |s| prune_mark_mark(s, glyphs)),
+        PositionLookup::Contextual(l) => {
+            retain_subtables(l, |s| prune_sequence_context(s, glyphs));
+        }
+        PositionLookup::ChainContextual(l) => {
+            retain_subtables(l, |s| prune_chained_context(s, glyphs));
+        }
+        PositionLookup::Extension(l) => retain_subtables(l, |s| match s {
+            PosExtension::Single(e) => prune_single_pos(&mut e.extension, glyphs),
+            PosExtension::Pair(e) => prune_pair_pos(&mut e.extension, glyphs),
+            PosExtension::Cursive(e) => prune_cursive(&mut e.extension, glyphs),
+            PosExtension::MarkToBase(e) => prune_mark_base(&mut e.extension, glyphs),
+            PosExtension::MarkToLig(e) => prune_mark_lig(&mut e.extension, glyphs),
+            PosExtension::MarkToMark(e) => prune_mark_mark(&mut e.extension, glyphs),
+            PosExtension::Contextual(e) => prune_sequence_context(&mut e.extension, glyphs),
+            PosExtension::ChainContextual(e) => prune_chained_context(&mut e.extension, glyphs),
+        }),
+    }
+}
+
+fn prune_single_pos(s: &mut SinglePos, glyphs: &Glyphs) -> bool {
+    match s {
+        SinglePos::Format1(s) => prune_coverage(&mut s.coverage, glyphs),
+        SinglePos::Format2(s) => {
+            retain_covered(&mut s.coverage, &mut s.value_records, glyphs, |_| true)
+        }
+    }
+}
+
+/// also drops the classes no kept glyph is in, since the class counts come from the class
+/// definitions
+fn prune_pair_pos(s: &mut PairPos, glyphs: &Glyphs) -> bool {
+    match s {
+        PairPos::Format1(s) => retain_covered(&mut s.coverage, &mut s.pair_sets, glyphs, |set| {
+            set.pair_value_records.retain(|r| keep(glyphs, r.second_glyph));
+            !set.pair_value_records.is_empty()
+        }),
+        PairPos::Format2(s) => {
+            if !prune_coverage(&mut s.coverage, glyphs) {
+                return false;
+            }
+            let used1 = s.coverage.iter().map(|g| s.class_def1.get(g)).chain([0]).collect();
+            let used2 = s
+                .class_def2
+                .iter()
+                .filter(|(g, _)| keep(glyphs, *g))
+                .map(|(_, c)| c)
+                .chain([0])
+                .collect();
+            s.class_def1 = renumber(&s.class_def1, &used1, glyphs).into();
+            s.class_def2 = renumber(&s.class_def2, &used2, glyphs).into();
+            pick(&mut s.class1_records, &used1);
+            for r in &mut s.class1_records {
+                pick(&mut r.class2_records, &used2);
+            }
+            true
+        }
+    }
+}
+
+fn prune_cursive(s: &mut CursivePosFormat1, glyphs: &Glyphs) -> bool {
+    retain_covered(&mut s.coverage, &mut s.entry_exit_record, glyphs, |_| true)
+}
+
+/// keeps the kept marks and renumbers their classes, since the class count comes from them.
+/// returns the old classes that are left, or `None` if no marks are
+fn prune_marks(
+    coverage: &mut CoverageTable,
+    marks: &mut MarkArray,
+    glyphs: &Glyphs,
+) -> Option<BTreeSet<u16>> {
+    if !retain_covered(coverage, &mut marks.mark_records, glyphs, |_| true) {
+        return None;
+    }
+    let used = marks.mark_records.iter().map(|r| r.mark_class).collect::<BTreeSet<_>>();
+    for r in &mut marks.mark_records {
+        r.mark_class = used.iter().position(|c| *c == r.mark_class).unwrap_or_default() as u16;
+    }
+    Some(used)
+}
+
+fn prune_mark_base(s: &mut MarkBasePosFormat1, glyphs: &Glyphs) -> bool {
+    let Some(used) = prune_marks(&mut s.mark_coverage, &mut s.mark_array, glyphs) else {
+        return false;
+    };
+    retain_covered(&mut s.base_coverage, &mut s.base_array.base_records, glyphs, |r| {
+        pick(&mut r.base_anchors, &used);
+        true
+    })
+}
+
+fn prune_mark_lig(s: &mut MarkLigPosFormat1, glyphs: &Glyphs) -> bool {
+    let Some(used) = prune_marks(&mut s.mark_coverage, &mut s.mark_array, glyphs) else {
+        return false;
+    };
+    let attaches = &mut s.ligature_array.ligature_attaches;
+    retain_covered(&mut s.ligature_coverage, attaches, glyphs, |a| {
+        for r in &mut a.component_records {
+            pick(&mut r.ligature_anchors, &used);
+        }
+        true
+    })
+}
+
+fn prune_mark_mark(s: &mut MarkMarkPosFormat1, glyphs: &Glyphs) -> bool {
+    let Some(used) = prune_marks(&mut s.mark1_coverage, &mut s.mark1_array, glyphs) else {
+        return false;
+    };
+    retain_covered(&mut s.mark2_coverage, &mut s.mark2_array.mark2_records, glyphs, |r| {
+        pick(&mut r.mark2_anchors, &used);
+        true
+    })
+}
+
+fn prune_gdef(gdef: &mut Gdef, glyphs: &Glyphs) {
+    if let Some(class_def) = gdef.glyph_class_def.as_mut() {
+        *class_def = prune_class_def(class_def, glyphs);
+    }
+    if let Some(list) = gdef.attach_list.as_mut() {
+        retain_covered(&mut list.coverage, &mut list.attach_points, glyphs, |_| true);
+    }
+    if let Some(list) = gdef.lig_caret_list.as_mut() {
+        retain_covered(&mut list.coverage, &mut list.lig_glyphs, glyphs, |_| true);
+    }
+    if let Some(class_def) = gdef.mark_attach_class_def.as_mut() {
+        *class_def = prune_class_def(class_def, glyphs);
+    }
+    if let Some(sets) = gdef.mark_glyph_sets_def.as_mut() {
+        for coverage in &mut sets.coverages {
+            prune_coverage(coverage, glyphs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use write_fonts::read::{
+        tables::{
+            gpos::{PairPos, PositionSubtables},
+            gsub::SubstitutionSubtables,
+        },
+        FontRef, ReadError, TableProvider as _,
+    };
+
+    use crate::{Font, FontFamily};
+
+    fn subset(file: &str, codepoints: &[u32]) -> (Vec<u8>, Vec<u8>) {
+        let path = format!("{}/out/fonts/{file}", env!("CARGO_MANIFEST_DIR"));
+        let bytes = std::fs::read(path).unwrap();
+        let font = Font { filename: file.into(), fontname: FontFamily::new("Sans"), bytes };
+        let subset = font.subset(codepoints).unwrap().font.bytes;
+        (font.bytes, subset)
+    }
+
+    fn all<T>(items: impl Iterator<Item = Result<T, ReadError>>) -> Vec<T> {
+        items.collect::<Result<_, _>>().unwrap()
+    }
+
+    /// the ligature glyphs in GSUB, reading every lookup and subtable
+    fn read_gsub(font: &FontRef) -> BTreeSet<u16> {
+        let mut ligatures = BTreeSet::new();
+        for lookup in all(font.gsub().unwrap().lookup_list().unwrap().lookups().iter()) {
+            match lookup.subtables().unwrap() {
+                SubstitutionSubtables::Single(s) => drop(all(s.iter())),
+                SubstitutionSubtables::Multiple(s) => drop(all(s.iter())),
+                SubstitutionSubtables::Alternate(s) => drop(all(s.iter())),
+                SubstitutionSubtables::Ligature(s) => {
+                    for s in all(s.iter()) {
+                        for set in all(s.ligature_sets().iter()) {
+                            let glyphs = all(set.ligatures().iter()).into_iter();
+                            ligatures.extend(glyphs.map(|l| l.ligature_glyph().to_u16()));
+                        }
+                    }
+                }
+                SubstitutionSubtables::Contextual(s) => drop(all(s.iter())),
+                SubstitutionSubtables::ChainContextual(s) => drop(all(s.iter())),
+                SubstitutionSubtables::Reverse(s) => drop(all(s.iter())),
+                SubstitutionSubtables::EmptyExtension => {}
+            }
+        }
+        ligatures
+    }
+
+    /// reads every lookup and subtable in GPOS, checking that the class counts of class-based
+    /// pair positioning match its class definitions. returns how many of those there are
+    fn read_gpos(font: &FontRef) -> usize {
+        let mut pair_classes = 0;
+        for lookup in all(font.gpos().unwrap().lookup_list().unwrap().lookups().iter()) {
+            match lookup.subtables().unwrap() {
+                PositionSubtables::Pair(s) => {
+                    for s in all(s.iter()) {
+                        let PairPos::Format2(s) = s else { continue };
+                        let count = |classes: &[(u16, u16)]| {
+                            let classes = classes.iter().map(|(_, c)| *c).chain([0]);
+                            let classes = classes.collect::<BTreeSet<_>>();
+                            assert_eq!(classes.last().map(|c| c + 1), Some(classes.len() as u16));
+                            classes.len() as u16
+                        };
+                        let pairs = |class_def: write_fonts::read::tables::layout::ClassDef| {
+                            class_def.iter().map(|(g, c)| (g.to_u16(), c)).collect::<Vec<_>>()
+                        };
+                        let class2_count = count(&pairs(s.class_def2().unwrap()));
+                        assert_eq!(count(&pairs(s.class_def1().unwrap())), s.class1_count());
+                        assert_eq!(class2_count, s.class2_count());
+                        let records = all(s.class1_records().iter());
+                        assert_eq!(records.len(), usize::from(s.class1_count()));
+                        for r in records {
+                            assert_eq!(r.class2_records().len(), usize::from(class2_count));
+                        }
+                        pair_classes += 1;
+                    }
+                }
+                PositionSubtables::Single(s) => drop(all(s.iter())),
+                PositionSubtables::Cursive(s) => drop(all(s.iter())),
+                PositionSubtables::MarkToBase(s) => drop(all(s.iter())),
+                PositionSubtables::MarkToLig(s) => drop(all(s.iter())),
+                PositionSubtables::MarkToMark(s) => drop(all(s.iter())),
+                PositionSubtables::Contextual(s) => drop(all(s.iter())),
+                PositionSubtables::ChainContextual(s) => drop(all(s.iter())),
+                PositionSubtables::EmptyExtension => {}
+            }
+        }
+        pair_classes
+    }
+
+    fn read_gdef(font: &FontRef) {
+        let gdef = font.gdef().unwrap();
+        gdef.glyph_class_def().transpose().unwrap();
+        gdef.attach_list().transpose().unwrap();
+        gdef.lig_caret_list().transpose().unwrap();
+        gdef.mark_attach_class_def().transpose().unwrap();
+        if let Some(sets) = gdef.mark_glyph_sets_def().transpose().unwrap() {
+            all(sets.coverages().iter());
+        }
+    }
+
+    /// the codepoints in every unicode `cmap` subtable
+    fn mapped(font: &[u8]) -> BTreeSet<u32> {
+        let face = ttf_parser::Face::parse(font, 0).unwrap();
+        let mut mapped = BTreeSet::new();
+        for subtable in face.tables().cmap.unwrap().subtables {
+            if subtable.is_unicode() {
+                subtable.codepoints(|c| {
+                    mapped.insert(c);
+                });
+            }
+        }
+        mapped
+    }
+
+    #[test]
+    fn conjunct() {
+        // क्ष and the dotted circle
+        let codepoints = [0x915, 0x937, 0x94d, 0x25cc];
+        let (original, subset) = subset("NotoSansDevanagari-Regular.ttf", &codepoints);
+        assert_eq!(mapped(&subset), BTreeSet::from(codepoints));
+        let face = ttf_parser::Face::parse(&original, 0).unwrap();
+        let kssa = (0..face.number_of_glyphs())
+            .find(|g| face.glyph_name(ttf_parser::GlyphId(*g)) == Some("k_ssa-deva"))
+            .unwrap();
+        let font = FontRef::new(&subset).unwrap();
+        assert!(read_gsub(&font).contains(&kssa));
+        let face = ttf_parser::Face::parse(&subset, 0).unwrap();
+        assert!(face.glyph_bounding_box(ttf_parser::GlyphId(kssa)).is_some());
+        read_gpos(&font);
+        read_gdef(&font);
+    }
+
+    #[test]
+    fn kerning_classes() {
+        let codepoints = "AVATAR Wo".chars().map(u32::from).collect::<Vec<_>>();
+        let (_, subset) = subset("NotoSans-Regular.ttf", &codepoints);
+        assert_eq!(mapped(&subset), codepoints.iter().copied().collect());
+        let font = FontRef::new(&subset).unwrap();
+        read_gsub(&font);
+        assert!(read_gpos(&font) > 0);
+        read_gdef(&font);
+    }
+}
@@ -0,0 +1,762 @@
+//! WOFF and WOFF2 encoding, and decoding them back to TTF/OTF
+
+use std::io::{Read as _, Write as _};
+
+use itertools::Itertools as _;
+
+use crate::build_sfnt;
+
+/// the tables WOFF2 refers to by index instead of by tag
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+// composite glyph flags
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x1;
+const WE_HAVE_A_SCALE: u16 = 0x8;
+const MORE_COMPONENTS: u16 = 0x20;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x40;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x80;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x100;
+
+/// a cursor over big-endian font data
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self { Self { data, pos: 0 } }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).ok_or("unexpected end of font data")?;
+        let res = self.data.get(self.pos..end).ok_or("unexpected end of font data")?;
+        self.pos += n;
+        Ok(res)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> { Ok(self.bytes(1)?[0]) }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> { Ok(self.u16()? as i16) }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// a WOFF2 255UInt16
+    fn u255(&mut self) -> Result<u16, String> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => 506 + u16::from(self.u8()?),
+            255 => 253 + u16::from(self.u8()?),
+            x => x.into(),
+        })
+    }
+
+    /// a WOFF2 UIntBase128
+    fn base128(&mut self) -> Result<u32, String> {
+        let mut res = 0u32;
+        for i in 0..5 {
+            let b = self.u8()?;
+            if (i == 0 && b == 0x80) || res >> 25 != 0 {
+                return Err("bad UIntBase128".into());
+            }
+            res = res << 7 | u32::from(b & 0x7f);
+            if b & 0x80 == 0 {
+                return Ok(res);
+            }
+        }
+        Err("UIntBase128 longer than 5 bytes".into())
+    }
+
+    /// a composite glyph's components, and whether it has instructions
+    fn components(&mut self) -> Result<(&'a [u8], bool), String> {
+        let start = self.pos;
+        let mut instructions = false;
+        loop {
+            let flags = self.u16()?;
+            let args = if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+            let scale = if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else {
+                0
+            };
+            self.bytes(2 + args + scale)?;
+            instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok((&self.data[start..self.pos], instructions));
+            }
+        }
+    }
+}
+
+fn write_u255(out: &mut Vec<u8>, x: u16) {
+    match x {
+        0..253 => out.push(x as u8),
+        253..506 => out.extend([255, (x - 253) as u8]),
+        506..762 => out.extend([254, (x - 506) as u8]),
+        _ => out.extend([253].into_iter().chain(x.to_be_bytes())),
+    }
+}
+
+fn write_base128(out: &mut Vec<u8>, x: u32) {
+    let groups = (0..5).rev().map(|i| (x >> (7 * i) & 0x7f) as u8).skip_while(|g| *g == 0);
+    let mut groups = groups.collect_vec();
+    if groups.is_empty() {
+        groups.push(0);
+    }
+    let last = groups.len() - 1;
+    out.extend(groups.into_iter().enumerate().map(|(i, g)| if i < last { g | 0x80 } else { g }));
+}
+
+/// a table's tag, checksum and data
+type Table<'a> = ([u8; 4], u32, &'a [u8]);
+
+/// the tables of an sfnt, in order
+fn tables(sfnt: &[u8]) -> Result<Vec<Table<'_>>, String> {
+    let raw = ttf_parser::RawFace::parse(sfnt, 0).map_err(|e| e.to_string())?;
+    raw.table_records
+        .into_iter()
+        .map(|r| {
+            Ok((r.tag.to_bytes(), r.check_sum, raw.table(r.tag).ok_or("table out of bounds")?))
+        })
+        .collect()
+}
+
+/// the size of the sfnt the tables make up
+fn sfnt_size(lengths: impl ExactSizeIterator<Item = usize>) -> u32 {
+    (12 + 16 * lengths.len() + lengths.map(|l| l.next_multiple_of(4)).sum::<usize>()) as u32
+}
+
+pub(crate) fn encode_woff(sfnt: &[u8]) -> Result<Vec<u8>, String> {
+    let tables = tables(sfnt)?;
+    let mut dir = vec![];
+    let mut data = vec![];
+    for (tag, checksum, table) in &tables {
+        let mut zlib = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::best());
+        zlib.write_all(table).map_err(|e| e.to_string())?;
+        let compressed = zlib.finish().map_err(|e| e.to_string())?;
+        let stored = if compressed.len() < table.len() { &compressed[..] } else { table };
+        let offset = 44 + 20 * tables.len() + data.len();
+        dir.extend(tag);
+        for x in [offset, stored.len(), table.len()] {
+            dir.extend((x as u32).to_be_bytes());
+        }
+        dir.extend(checksum.to_be_bytes());
+        data.extend(stored);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    let mut out = b"wOFF".to_vec();
+    out.extend(&sfnt[..4]);
+    out.extend(((44 + dir.len() + data.len()) as u32).to_be_bytes());
+    out.extend((tables.len() as u16).to_be_bytes());
+    out.extend([0; 2]);
+    out.extend(sfnt_size(tables.iter().map(|t| t.2.len())).to_be_bytes());
+    out.extend([0, 1, 0, 0]);
+    // no metadata or private data
+    out.extend([0; 20]);
+    out.extend(dir);
+    out.extend(data);
+    Ok(out)
+}
+
+fn decode_woff(woff: &[u8]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(woff);
+    r.bytes(4)?;
+    let flavor = r.bytes(4)?.try_into().unwrap();
+    r.u32()?;
+    let count = r.u16()?;
+    if count == 0 {
+        return Err("WOFF file without tables".into());
+    }
+    r.bytes(30)?;
+    let mut tables = vec![];
+    for _ in 0..count {
+        let tag: [u8; 4] = r.bytes(4)?.try_into().unwrap();
+        let (offset, stored, length) = (r.u32()? as usize, r.u32()? as usize, r.u32()? as usize);
+        r.u32()?;
+        let data = Reader { data: woff, pos: offset }.bytes(stored)?;
+        let table = if stored < length {
+            // the length is untrusted, so it only limits how much gets decompressed
+            let mut table = vec![];
+            flate2::read::ZlibDecoder::new(data)
+                .take(length as u64 + 1)
+                .read_to_end(&mut table)
+                .map_err(|e| e.to_string())?;
+            table
+        } else {
+            data.to_vec()
+        };
+        if table.len() != length {
+            return Err(format!("{} has the wrong length", String::from_utf8_lossy(&tag)));
+        }
+        tables.push((tag, table));
+    }
+    build_sfnt(flavor, tables)
+}
+
+pub(crate) fn encode_woff2(sfnt: &[u8]) -> Result<Vec<u8>, String> {
+    let mut tables =
+        tables(sfnt)?.into_iter().map(|(tag, _, data)| (tag, data.to_vec())).collect_vec();
+    let find = |tag: &[u8; 4]| tables.iter().position(|t| t.0 == *tag);
+    let glyf = match (find(b"glyf"), find(b"loca"), find(b"head"), find(b"maxp")) {
+        (Some(glyf), Some(loca), Some(head), Some(maxp)) => {
+            let index_format = Reader { data: &tables[head].1, pos: 50 }.u16()?;
+            let glyphs = Reader { data: &tables[maxp].1, pos: 4 }.u16()?;
+            let glyf = transform_glyf(&tables[glyf].1, &tables[loca].1, index_format, glyphs)?;
+            // the font is losslessly modified
+            let head = &mut tables[head].1;
+            head[16] |= 0x08;
+            Some(glyf)
+        }
+        _ => None,
+    };
+    // loca has to come right after glyf
+    tables.sort_by_key(|(tag, _)| if tag == b"loca" { *b"glyg" } else { *tag });
+    let mut dir = vec![];
+    let mut stream = vec![];
+    for (tag, data) in &tables {
+        let index = KNOWN_TAGS.iter().position(|t| *t == tag);
+        let transformed = match &glyf {
+            Some(glyf) if tag == b"glyf" => Some(&glyf[..]),
+            Some(_) if tag == b"loca" => Some(&[][..]),
+            _ => None,
+        };
+        // glyf and loca have the null transform as version 3 instead of 0
+        let version =
+            if transformed.is_none() && [b"glyf", b"loca"].contains(&tag) { 3 } else { 0 };
+        dir.push(index.unwrap_or(63) as u8 | version << 6);
+        if index.is_none() {
+            dir.extend(tag);
+        }
+        write_base128(&mut dir, data.len() as u32);
+        if let Some(transformed) = transformed {
+            write_base128(&mut dir, transformed.len() as u32);
+            stream.extend(transformed);
+        } else {
+            stream.extend(data);
+        }
+    }
+    let mut compressed = vec![];
+    let params = brotli::enc::BrotliEncoderParams { quality: 11, lgwin: 22, ..Default::default() };
+    brotli::BrotliCompress(&mut &stream[..], &mut compressed, &params)
+        .map_err(|e| e.to_string())?;
+    let mut out = b"wOF2".to_vec();
+    out.extend(&sfnt[..4]);
+    out.extend(((48 + dir.len() + compressed.len()).next_multiple_of(4) as u32).to_be_bytes());
+    out.extend((tables.len() as u16).to_be_bytes());
+    out.extend([0; 2]);
+    out.extend(sfnt_size(tables.iter().map(|t| t.1.len())).to_be_bytes());
+    out.extend((compressed.len() as u32).to_be_bytes());
+    out.extend([0, 1, 0, 0]);
+    // no metadata or private data
+    out.extend([0; 20]);
+    out.extend(dir);
+    out.extend(compressed);
+    out.resize(out.len().next_multiple_of(4), 0);
+    Ok(out)
+}
+
+fn decode_woff2(woff2: &[u8]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(woff2);
+    r.bytes(4)?;
+    let flavor = r.bytes(4)?.try_into().unwrap();
+    r.u32()?;
+    let count = r.u16()?;
+    if count == 0 {
+        return Err("WOFF2 file without tables".into());
+    }
+    r.bytes(6)?;
+    let compressed_size = r.u32()? as usize;
+    r.bytes(24)?;
+    let mut dir = vec![];
+    for _ in 0..count {
+        let flags = r.u8()?;
+        let tag: [u8; 4] = match flags & 0x3f {
+            63 => r.bytes(4)?.try_into().unwrap(),
+            i => *KNOWN_TAGS[usize::from(i)],
+        };
+        let version = flags >> 6;
+        let length = r.base128()? as usize;
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version == 0,
+            b"hmtx" => version == 1,
+            _ => false,
+        };
+        let stored = if transformed { r.base128()? as usize } else { length };
+        dir.push((tag, transformed, stored));
+    }
+    let total =
+        dir.iter().try_fold(0usize, |sum, t| sum.checked_add(t.2)).ok_or("bad table size")?;
+    let mut stream = vec![];
+    brotli::Decompressor::new(r.bytes(compressed_size)?, 4096)
+        .take(total as u64)
+        .read_to_end(&mut stream)
+        .map_err(|e| e.to_string())?;
+    let mut r = Reader::new(&stream);
+    let mut tables = vec![];
+    let mut glyf = None;
+    for (tag, transformed, stored) in dir {
+        let data = r.bytes(stored)?;
+        match (&tag, transformed) {
+            (b"glyf", true) => glyf = Some(reconstruct_glyf(data)?),
+            (b"loca", true) => {}
+            (_, true) => return Err("transformed hmtx isn't supported".into()),
+            _ => tables.push((tag, data.to_vec())),
+        }
+    }
+    if let Some((glyf, loca, index_format)) = glyf {
+        let head = tables.iter_mut().find(|t| t.0 == *b"head").ok_or("no head table")?;
+        head.1
+            .get_mut(50..52)
+            .ok_or("head is too short")?
+            .copy_from_slice(&index_format.to_be_bytes());
+        tables.extend([(*b"glyf", glyf), (*b"loca", loca)]);
+    }
+    build_sfnt(flavor, tables)
+}
+
+/// an sfnt from a WOFF or WOFF2 file
+pub(crate) fn decode(font: &[u8]) -> Result<Vec<u8>, String> {
+    match font.get(..4) {
+        Some(b"wOFF") => decode_woff(font),
+        Some(b"wOF2") => decode_woff2(font),
+        _ => Err("not a WOFF or WOFF2 file".into()),
+    }
+}
+
+/// the WOFF2 transformed glyf table, which stores the parts of every glyph in separate streams
+/// that compress better, and leaves out loca
+fn transform_glyf(
+    glyf: &[u8],
+    loca: &[u8],
+    index_format: u16,
+    glyphs: u16,
+) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(loca);
+    let offsets = (0..=glyphs)
+        .map(|_| Ok(if index_format == 0 { usize::from(r.u16()?) * 2 } else { r.u32()? as usize }))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut contours = vec![];
+    let mut points = vec![];
+    let mut flags = vec![];
+    let mut data = vec![];
+    let mut composites = vec![];
+    let mut bbox_bitmap = vec![0u8; usize::from(glyphs).div_ceil(32) * 4];
+    let mut bboxes = vec![];
+    let mut instructions = vec![];
+    for (i, (start, end)) in offsets.iter().tuple_windows().enumerate() {
+        let glyph = glyf.get(*start..*end).ok_or("glyph out of bounds")?;
+        let mut r = Reader::new(glyph);
+        let n = if glyph.is_empty() { 0 } else { r.i16()? };
+        contours.extend(n.to_be_bytes());
+        // a glyph without contours has nothing to draw, so it's stored as empty
+        if n == 0 {
+            continue;
+        }
+        let bbox = r.bytes(8)?;
+        if n < 0 {
+            let (components, has_instructions) = r.components()?;
+            composites.extend(components);
+            bbox_bitmap[i >> 3] |= 0x80 >> (i & 7);
+            bboxes.extend(bbox);
+            if has_instructions {
+                let length = r.u16()?;
+                write_u255(&mut data, length);
+                instructions.extend(r.bytes(length.into())?);
+            }
+            continue;
+        }
+        let ends = (0..n).map(|_| r.u16()).collect::<Result<Vec<_>, String>>()?;
+        let mut last = -1;
+        for end in &ends {
+            let count =
+                u16::try_from(i32::from(*end) - last).map_err(|_| "contour ends out of order")?;
+            write_u255(&mut points, count);
+            last = i32::from(*end);
+        }
+        let length = r.u16()?;
+        let program = r.bytes(length.into())?;
+        let count = ends.last().map_or(0, |e| usize::from(*e) + 1);
+        let mut point_flags = Vec::with_capacity(count);
+        while point_flags.len() < count {
+            let flag = r.u8()?;
+            let repeat = if flag & 0x08 != 0 { r.u8()? } else { 0 };
+            point_flags.extend(std::iter::repeat_n(flag, usize::from(repeat) + 1));
+        }
+        let mut coordinate = |short: u8, same: u8| {
+            point_flags
+                .iter()
+                .map(|f| match (f & short != 0, f & same != 0) {
+                    (true, positive) => {
+                        let x = i32::from(r.u8()?);
+                        Ok(if positive { x } else { -x })
+                    }
+                    (false, true) => Ok(0),
+                    (false, false) => Ok(i32::from(r.i16()?)),
+                })
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let (dx, dy) = (coordinate(0x02, 0x10)?, coordinate(0x04, 0x20)?);
+        let (mut x, mut y) = (0, 0);
+        let mut bounds = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+        for ((flag, dx), dy) in point_flags.iter().zip(dx).zip(dy) {
+            write_triplet(&mut flags, &mut data, flag & 1 != 0, dx, dy);
+            (x, y) = (x + dx, y + dy);
+            bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+        }
+        let computed = [bounds.0, bounds.1, bounds.2, bounds.3]
+            .into_iter()
+            .flat_map(|b| (b as i16).to_be_bytes())
+            .collect_vec();
+        if computed != bbox {
+            bbox_bitmap[i >> 3] |= 0x80 >> (i & 7);
+            bboxes.extend(bbox);
+        }
+        write_u255(&mut data, length);
+        instructions.extend(program);
+    }
+    let streams =
+        [contours, points, flags, data, composites, [bbox_bitmap, bboxes].concat(), instructions];
+    let mut out = vec![];
+    for x in [0, 0, glyphs, index_format] {
+        out.extend(x.to_be_bytes());
+    }
+    for stream in &streams {
+        out.extend((stream.len() as u32).to_be_bytes());
+    }
+    out.extend(streams.concat());
+    Ok(out)
+}
+
+/// encodes a point's offset from the last one as a flag and 1 to 4 bytes
+fn write_triplet(flags: &mut Vec<u8>, data: &mut Vec<u8>, on_curve: bool, x: i32, y: i32) {
+    let (ax, ay) = (x.unsigned_abs(), y.unsigned_abs());
+    let on_curve = if on_curve { 0 } else { 128 };
+    let (x_sign, y_sign) = (u8::from(x >= 0), u8::from(y >= 0));
+    let signs = x_sign + 2 * y_sign;
+    if x == 0 && ay < 1280 {
+        flags.push(on_curve + ((ay & 0xf00) >> 7) as u8 + y_sign);
+        data.push(ay as u8);
+    } else if y == 0 && ax < 1280 {
+        flags.push(on_curve + 10 + ((ax & 0xf00) >> 7) as u8 + x_sign);
+        data.push(ax as u8);
+    } else if ax < 65 && ay < 65 {
+        let (bx, by) = (ax - 1, ay - 1);
+        flags.push(on_curve + 20 + (bx & 0x30) as u8 + ((by & 0x30) >> 2) as u8 + signs);
+        data.push(((bx & 0xf) << 4 | by & 0xf) as u8);
+    } else if ax < 769 && ay < 769 {
+        let (bx, by) = (ax - 1, ay - 1);
+        flags.push(
+            on_curve + 84 + 12 * ((bx & 0x300) >> 8) as u8 + ((by & 0x300) >> 6) as u8 + signs,
+        );
+        data.extend([bx as u8, by as u8]);
+    } else if ax < 4096 && ay < 4096 {
+        flags.push(on_curve + 120 + signs);
+        data.extend([(ax >> 4) as u8, ((ax & 0xf) << 4 | ay >> 8) as u8, ay as u8]);
+    } else {
+        flags.push(on_curve + 124 + signs);
+        data.extend([(ax >> 8) as u8, ax as u8, (ay >> 8) as u8, ay as u8]);
+    }
+}
+
+/// decodes the points of a simple glyph from their flags and the glyph stream
+fn read_triplets(flags: &[u8], r: &mut Reader) -> Result<Vec<(bool, i32, i32)>, String> {
+    let sign = |flag: u8, x: i32| if flag & 1 != 0 { x } else { -x };
+    flags
+        .iter()
+        .map(|flag| {
+            let on_curve = flag >> 7 == 0;
+            let flag = flag & 0x7f;
+            let (dx, dy) = match flag {
+                0..10 => (0, sign(flag, i32::from(flag & 14) << 7 | i32::from(r.u8()?))),
+                10..20 => (sign(flag, i32::from((flag - 10) & 14) << 7 | i32::from(r.u8()?)), 0),
+                20..84 => {
+                    let (b0, b1) = (i32::from(flag - 20), i32::from(r.u8()?));
+                    (
+                        sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+                        sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)),
+                    )
+                }
+                84..120 => {
+                    let b0 = i32::from(flag - 84);
+                    let (b1, b2) = (i32::from(r.u8()?), i32::from(r.u8()?));
+                    (
+                        sign(flag, 1 + ((b0 / 12) << 8) + b1),
+                        sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+                    )
+                }
+                120..124 => {
+                    let b = r.bytes(3)?;
+                    let (b0, b1, b2) = (i32::from(b[0]), i32::from(b[1]), i32::from(b[2]));
+                    (sign(flag, b0 << 4 | b1 >> 4), sign(flag >> 1, (b1 & 0x0f) << 8 | b2))
+                }
+                _ => {
+                    let b = r.bytes(4)?.iter().map(|b| i32::from(*b)).collect_vec();
+                    (sign(flag, b[0] << 8 | b[1]), sign(flag >> 1, b[2] << 8 | b[3]))
+                }
+            };
+            Ok((on_curve, dx, dy))
+        })
+        .collect()
+}
+
+/// the glyf and loca tables and the index format from a transformed glyf table
+fn reconstruct_glyf(transformed: &[u8]) -> Result<(Vec<u8>, Vec<u8>, u16), String> {
+    let mut r = Reader::new(transformed);
+    r.u16()?;
+    r.u16()?;
+    let glyphs = usize::from(r.u16()?);
+    let index_format = r.u16()?;
+    let sizes = (0..7).map(|_| Ok(r.u32()? as usize)).collect::<Result<Vec<_>, String>>()?;
+    let mut streams =
+        sizes.iter().map(|s| r.bytes(*s).map(Reader::new)).collect::<Result<Vec<_>, String>>()?;
+    let [contours, points, flags, data, composites, bboxes, instructions] = &mut streams[..] else {
+        unreachable!()
+    };
+    let bbox_bitmap = bboxes.bytes(glyphs.div_ceil(32) * 4)?;
+    let mut glyf = vec![];
+    let mut offsets = vec![0];
+    for i in 0..glyphs {
+        let n = contours.i16()?;
+        let explicit_bbox = bbox_bitmap[i >> 3] & (0x80 >> (i & 7)) != 0;
+        if n < 0 {
+            if !explicit_bbox {
+                return Err("composite glyph without a bounding box".into());
+            }
+            glyf.extend(n.to_be_bytes());
+            glyf.extend(bboxes.bytes(8)?);
+            let (components, has_instructions) = composites.components()?;
+            glyf.extend(components);
+            if has_instructions {
+                let length = data.u255()?;
+                glyf.extend(length.to_be_bytes());
+                glyf.extend(instructions.bytes(length.into())?);
+            }
+        } else if n > 0 {
+            let mut ends = vec![];
+            let mut count = 0usize;
+            for _ in 0..n {
+                count += usize::from(points.u255()?);
+                let end = count.checked_sub(1).and_then(|e| u16::try_from(e).ok());
+                ends.push(end.ok_or("bad contour point count")?);
+            }
+            let triplets = read_triplets(flags.bytes(count)?, data)?;
+            let length = data.u255()?;
+            let program = instructions.bytes(length.into())?;
+            glyf.extend(n.to_be_bytes());
+            if explicit_bbox {
+                glyf.extend(bboxes.bytes(8)?);
+            } else {
+                let (mut x, mut y) = (0, 0);
+                let mut bounds = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+                for (_, dx, dy) in &triplets {
+                    (x, y) = (x + dx, y + dy);
+                    bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+                }
+                for b in [bounds.0, bounds.1, bounds.2, bounds.3] {
+                    glyf.extend((b as i16).to_be_bytes());
+                }
+            }
+            for end in ends {
+                glyf.extend(end.to_be_bytes());
+            }
+            glyf.extend(length.to_be_bytes());
+            glyf.extend(program);
+            write_points(&mut glyf, &triplets);
+        }
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        offsets.push(glyf.len());
+    }
+    // short offsets only reach so far
+    let index_format = if index_format == 0 && glyf.len() > 0x1fffe { 1 } else { index_format };
+    let loca = offsets
+        .into_iter()
+        .flat_map(|o| {
+            if index_format == 0 {
+                ((o / 2) as u16).to_be_bytes().to_vec()
+            } else {
+                (o as u32).to_be_bytes().to_vec()
+            }
+        })
+        .collect();
+    Ok((glyf, loca, index_format))
+}
+
+/// the flags and coordinates of a simple glyph, with short vectors and repeated flags
+fn write_points(glyf: &mut Vec<u8>, points: &[(bool, i32, i32)]) {
+    let mut xs = vec![];
+    let mut ys = vec![];
+    let flags = points
+        .iter()
+        .map(|(on_curve, dx, dy)| {
+            let mut flag = u8::from(*on_curve);
+            for (d, coords, short, same) in [(dx, &mut xs, 0x02, 0x10), (dy, &mut ys, 0x04, 0x20)] {
+                match d {
+                    0 => flag |= same,
+                    -255..=255 => {
+                        flag |= short | if *d > 0 { same } else { 0 };
+                        coords.push(d.unsigned_abs() as u8);
+                    }
+                    _ => coords.extend((*d as i16).to_be_bytes()),
+                }
+            }
+            flag
+        })
+        .collect_vec();
+    for (flag, run) in flags.into_iter().dedup_with_count().map(|(n, f)| (f, n)) {
+        let mut run = run;
+        while run > 0 {
+            let n = run.min(256);
+            if n > 1 {
+                glyf.extend([flag | 0x08, (n - 1) as u8]);
+            } else {
+                glyf.push(flag);
+            }
+            run -= n;
+        }
+    }
+    glyf.extend(xs);
+    glyf.extend(ys);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a glyph's outline as the segments ttf-parser reads
+    #[derive(Debug, Default, PartialEq)]
+    struct Outline(Vec<(char, Vec<f32>)>);
+
+    impl ttf_parser::OutlineBuilder for Outline {
+        fn move_to(&mut self, x: f32, y: f32) { self.0.push(('M', vec![x, y])); }
+
+        fn line_to(&mut self, x: f32, y: f32) { self.0.push(('L', vec![x, y])); }
+
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0.push(('Q', vec![x1, y1, x, y]));
+        }
+
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0.push(('C', vec![x1, y1, x2, y2, x, y]));
+        }
+
+        fn close(&mut self) { self.0.push(('Z', vec![])); }
+    }
+
+    fn outlines(sfnt: &[u8]) -> Vec<(Option<ttf_parser::Rect>, Outline)> {
+        let face = ttf_parser::Face::parse(sfnt, 0).unwrap();
+        (0..face.number_of_glyphs())
+            .map(|g| {
+                let mut outline = Outline::default();
+                let bbox = face.outline_glyph(ttf_parser::GlyphId(g), &mut outline);
+                (bbox, outline)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let sfnt = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/out/fonts/NotoSansAdlam-Regular.ttf"
+        ))
+        .unwrap();
+        let expected = outlines(&sfnt);
+        for encoded in [encode_woff(&sfnt).unwrap(), encode_woff2(&sfnt).unwrap()] {
+            let decoded = decode(&encoded).unwrap();
+            assert_eq!(outlines(&decoded), expected);
+        }
+    }
+
+    #[test]
+    fn triplets() {
+        let values = [
+            0, 1, -1, 64, -64, 65, -65, 768, -768, 769, -769, 1279, -1279, 1280, -1280, 4095,
+            -4095, 4096, -4096, 32767, -32768,
+        ];
+        let points = values
+            .iter()
+            .cartesian_product(values)
+            .enumerate()
+            .map(|(i, (x, y))| (i % 3 == 0, *x, y))
+            .collect_vec();
+        let (mut flags, mut data) = (vec![], vec![]);
+        for (on_curve, x, y) in &points {
+            write_triplet(&mut flags, &mut data, *on_curve, *x, *y);
+        }
+        let mut r = Reader::new(&data);
+        assert_eq!(read_triplets(&flags, &mut r).unwrap(), points);
+        assert_eq!(r.pos, data.len());
+    }
+
+    #[test]
+    fn base128() {
+        for x in [0, 1, 127, 128, 16383, 16384, 0x0fff_ffff, 0x1000_0000, u32::MAX] {
+            let mut out = vec![];
+            write_base128(&mut out, x);
+            assert_eq!(Reader::new(&out).base128(), Ok(x));
+        }
+        // leading zeros, more than 32 bits and more than 5 bytes
+        for bad in [&[0x80, 0x01][..], &[0x90, 0x80, 0x80, 0x80, 0x00], &[0x81; 6]] {
+            assert!(Reader::new(bad).base128().is_err());
+        }
+    }
+
+    #[test]
+    fn u255() {
+        for x in [0, 252, 253, 505, 506, 761, 762, u16::MAX] {
+            let mut out = vec![];
+            write_u255(&mut out, x);
+            assert_eq!(Reader::new(&out).u255(), Ok(x));
+        }
+    }
+
+    #[test]
+    fn empty_first_contour() {
+        // one glyph with one contour of no points
+        let mut transformed = [0u16, 0, 1, 0].iter().flat_map(|x| x.to_be_bytes()).collect_vec();
+        for size in [2u32, 1, 0, 0, 0, 4, 0] {
+            transformed.extend(size.to_be_bytes());
+        }
+        transformed.extend([0, 1, 0, 0, 0, 0, 0]);
+        assert!(reconstruct_glyf(&transformed).is_err());
+    }
+
+    #[test]
+    fn malformed_headers() {
+        // headers of files without tables
+        let mut woff = [0; 44];
+        woff[..8].copy_from_slice(b"wOFF\0\x01\0\0");
+        let mut woff2 = [0; 48];
+        woff2[..8].copy_from_slice(b"wOF2\0\x01\0\0");
+        assert!(decode(&woff).is_err());
+        assert!(decode(&woff2).is_err());
+        assert!(decode(&woff[..20]).is_err());
+        assert!(build_sfnt(*b"\0\x01\0\0", vec![]).is_err());
+        let tables = (0..0x1000u32).map(|i| (i.to_be_bytes(), vec![])).collect();
+        assert!(build_sfnt(*b"\0\x01\0\0", tables).is_err());
+    }
+
+    #[test]
+    fn no_contours() {
+        // no contours, a bounding box and two bytes of instructions
+        let glyf = [&[0; 10][..], &[0, 2, 0xb0, 0]].concat();
+        let transformed = transform_glyf(&glyf, &[0, 0, 0, 7], 0, 1).unwrap();
+        assert_eq!(reconstruct_glyf(&transformed), Ok((vec![], vec![0; 4], 0)));
+    }
+}